@@ -1,4 +1,13 @@
+use std::path::Path;
+use std::time::Duration;
+
 use crate::{AuthType, RestClient};
+use crate::login::Login;
+use crate::oauth2::TokenCache;
+use crate::rate_limit::RateLimiter;
+use crate::request_options::RequestOptions;
+use crate::response::RestClientError;
+use crate::transport::{ClientIdentity, HttpSettings, HttpVersion, ReqwestTransport, Transport};
 
 #[derive(Debug)]
 pub struct ClientConfigurationBuilder {
@@ -6,6 +15,11 @@ pub struct ClientConfigurationBuilder {
     auth_type: Option<AuthType>,
     basic_auth: Option<(String, String)>,
     token: Option<String>,
+    http: HttpSettings,
+    transport: Option<Box<dyn Transport>>,
+    rate_limit: Option<(f64, usize)>,
+    options: RequestOptions,
+    login: Option<Login>,
 }
 
 impl ClientConfigurationBuilder {
@@ -15,6 +29,11 @@ impl ClientConfigurationBuilder {
             auth_type: None,
             basic_auth: None,
             token: None,
+            http: HttpSettings::default(),
+            transport: None,
+            rate_limit: None,
+            options: RequestOptions::default(),
+            login: None,
         }
     }
 
@@ -38,13 +57,108 @@ impl ClientConfigurationBuilder {
         self
     }
 
-    pub fn build(self) -> RestClient {
-        RestClient {
+    pub fn connect_timeout(mut self, timeout: Duration) -> ClientConfigurationBuilder {
+        self.http.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> ClientConfigurationBuilder {
+        self.http.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn total_timeout(mut self, timeout: Duration) -> ClientConfigurationBuilder {
+        self.http.total_timeout = Some(timeout);
+        self
+    }
+
+    pub fn pool_size(mut self, pool_size: usize) -> ClientConfigurationBuilder {
+        self.http.pool_size = Some(pool_size);
+        self
+    }
+
+    pub fn keep_alive(mut self, keep_alive: bool) -> ClientConfigurationBuilder {
+        self.http.keep_alive = keep_alive;
+        self
+    }
+
+    pub fn http_version(mut self, http_version: HttpVersion) -> ClientConfigurationBuilder {
+        self.http.http_version = http_version;
+        self
+    }
+
+    /// Trusts the PEM certificates in `path` as well as the system roots.
+    pub fn ca_bundle<P: AsRef<Path>>(mut self, path: P) -> ClientConfigurationBuilder {
+        self.http.tls.ca_bundle = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Presents a PEM client certificate, optionally followed by its chain, with its PEM key.
+    pub fn client_pem<P: AsRef<Path>>(mut self, cert: P, key: P) -> ClientConfigurationBuilder {
+        self.http.tls.identity = Some(ClientIdentity::Pem {
+            cert: cert.as_ref().to_path_buf(),
+            key: key.as_ref().to_path_buf(),
+        });
+        self
+    }
+
+    pub fn client_pkcs12<P: AsRef<Path>>(mut self, path: P, password: &str) -> ClientConfigurationBuilder {
+        self.http.tls.identity = Some(ClientIdentity::Pkcs12 {
+            path: path.as_ref().to_path_buf(),
+            password: password.to_owned(),
+        });
+        self
+    }
+
+    /// Accepts any server certificate. A warning is printed when the client is built.
+    pub fn insecure_skip_verify(mut self, insecure: bool) -> ClientConfigurationBuilder {
+        self.http.tls.insecure_skip_verify = insecure;
+        self
+    }
+
+    /// Logs in with the basic auth credentials to get the bearer or JWT token, instead of using `token`.
+    pub fn login(mut self, login: Login) -> ClientConfigurationBuilder {
+        self.login = Some(login);
+        self
+    }
+
+    /// Headers, query parameters and cookies sent with every request.
+    pub fn options(mut self, options: RequestOptions) -> ClientConfigurationBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Sends at most `per_second` requests a second across all threads sharing the client,
+    /// allowing bursts of up to `burst` requests after idling.
+    pub fn rate_limit(mut self, per_second: f64, burst: usize) -> ClientConfigurationBuilder {
+        self.rate_limit = Some((per_second, burst));
+        self
+    }
+
+    /// Sends requests through `transport` instead of over the network with reqwest.
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> ClientConfigurationBuilder {
+        self.transport = Some(Box::new(transport));
+        self
+    }
+
+    /// Builds the client, along with the one connection pool it shares between all its requests.
+    /// Fails when the TLS settings name certificates or keys which can not be loaded.
+    pub fn build(self) -> Result<RestClient, RestClientError> {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => Box::new(ReqwestTransport::new(&self.http)?),
+        };
+        Ok(RestClient {
             root_url: self.root_url,
             auth_type: self.auth_type,
             basic_auth: self.basic_auth,
             token: self.token,
-        }
+            transport,
+            rate_limiter: self.rate_limit.map(|(per_second, burst)| RateLimiter::new(per_second, burst)),
+            options: self.options,
+            login: self.login,
+            token_cache: TokenCache::default(),
+        })
     }
 }
 
@@ -58,9 +172,45 @@ mod config_builder {
             .basic_auth("foo", "bar")
             .auth_type(AuthType::Basic)
             .root_url("http://localhost:8000")
-            .build();
+            .build()
+            .unwrap();
         assert_eq!(&config.root_url, "http://localhost:8000");
         assert_eq!(config.auth_type.unwrap(), AuthType::Basic);
         assert_eq!(config.basic_auth, Some(("foo".to_owned(), "bar".to_owned())));
     }
+
+    #[test]
+    fn test_http_settings() {
+        let builder = ClientConfigurationBuilder::new()
+            .connect_timeout(Duration::from_secs(1))
+            .read_timeout(Duration::from_secs(2))
+            .total_timeout(Duration::from_secs(5))
+            .pool_size(4)
+            .keep_alive(false)
+            .http_version(HttpVersion::Http2);
+        assert_eq!(builder.http.connect_timeout, Some(Duration::from_secs(1)));
+        assert_eq!(builder.http.read_timeout, Some(Duration::from_secs(2)));
+        assert_eq!(builder.http.total_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(builder.http.pool_size, Some(4));
+        assert!(!builder.http.keep_alive);
+        assert_eq!(builder.http.http_version, HttpVersion::Http2);
+    }
+
+    #[test]
+    fn test_tls_settings() {
+        let builder = ClientConfigurationBuilder::new()
+            .ca_bundle("/etc/vaccine/ca.pem")
+            .client_pkcs12("/etc/vaccine/client.p12", "s3cret")
+            .insecure_skip_verify(true);
+        assert_eq!(builder.http.tls.ca_bundle, Some("/etc/vaccine/ca.pem".into()));
+        assert_eq!(format!("{:?}", builder.http.tls.identity.unwrap()), r#"Pkcs12 { path: "/etc/vaccine/client.p12" }"#);
+        assert!(builder.http.tls.insecure_skip_verify);
+
+        let builder = ClientConfigurationBuilder::new().client_pem("client.pem", "client.key");
+        assert_eq!(builder.http.tls.identity, Some(ClientIdentity::Pem {
+            cert: "client.pem".into(),
+            key: "client.key".into(),
+        }));
+        assert!(!builder.http.tls.insecure_skip_verify);
+    }
 }
@@ -4,16 +4,91 @@ extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
 
-use reqwest::{Client, Error, RequestBuilder, Response};
+use std::time::Duration;
+
 use serde_json::Value;
 
+use crate::login::Login;
+use crate::oauth2::{parse_token_response, token_request, TokenCache};
+use crate::rate_limit::RateLimiter;
+use crate::request_options::RequestOptions;
+use crate::response::{RestClientError, RestResponse};
+use crate::transport::{ReqwestTransport, RestRequest, Transport};
+
 pub mod config_builder;
+pub mod login;
+mod oauth2;
+pub mod rate_limit;
+pub mod request_options;
+pub mod response;
+pub mod signing;
+pub mod transport;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub enum AuthType {
     Basic,
     Bearer,
     JWT,
+    /// A key sent as is, under `name`, in a header or the query string.
+    ApiKey { name: String, location: ApiKeyLocation },
+    /// A bearer token fetched from `token_url` with the client id and secret held as basic auth
+    /// credentials, cached and refreshed before it expires.
+    OAuth2ClientCredentials { token_url: String, scope: Option<String> },
+    /// An HMAC-SHA256 signature over the canonical string, keyed with the token as the secret.
+    /// See `signing::canonical_string` for the placeholders of the template.
+    Signed {
+        #[serde(default = "signing::default_canonical_string")]
+        canonical_string: String,
+        #[serde(default = "signing::default_signature_header")]
+        signature_header: String,
+        #[serde(default = "signing::default_timestamp_header")]
+        timestamp_header: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyLocation {
+    Header,
+    Query,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Patch => "PATCH",
+            Method::Delete => "DELETE",
+            Method::Head => "HEAD",
+            Method::Options => "OPTIONS",
+        }
+    }
+
+    pub(crate) fn to_reqwest(self) -> reqwest::Method {
+        match self {
+            Method::Get => reqwest::Method::GET,
+            Method::Post => reqwest::Method::POST,
+            Method::Put => reqwest::Method::PUT,
+            Method::Patch => reqwest::Method::PATCH,
+            Method::Delete => reqwest::Method::DELETE,
+            Method::Head => reqwest::Method::HEAD,
+            Method::Options => reqwest::Method::OPTIONS,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -22,6 +97,11 @@ pub struct RestClient {
     auth_type: Option<AuthType>,
     basic_auth: Option<(String, String)>,
     token: Option<String>,
+    transport: Box<dyn Transport>,
+    rate_limiter: Option<RateLimiter>,
+    options: RequestOptions,
+    login: Option<Login>,
+    token_cache: TokenCache,
 }
 
 impl RestClient {
@@ -36,6 +116,11 @@ impl RestClient {
             auth_type,
             basic_auth: basic_auth.map(|(a, b)| (a.to_owned(), b.to_owned())),
             token: token.map(|token| token.to_owned()),
+            transport: Box::new(ReqwestTransport::default()),
+            rate_limiter: None,
+            options: RequestOptions::default(),
+            login: None,
+            token_cache: TokenCache::default(),
         }
     }
 
@@ -47,55 +132,163 @@ impl RestClient {
         }
     }
 
-    fn apply_auth_to_request(&self, auth_type: &AuthType, r: RequestBuilder) -> RequestBuilder {
-        match auth_type {
+    /// The request with its auth applied, and the bearer, JWT or OAuth2 token it carries.
+    fn apply_auth_to_request(&self, auth_type: &AuthType, r: RestRequest)
+                             -> Result<(RestRequest, Option<String>), RestClientError> {
+        let mut sent_token = None;
+        let r = match auth_type {
             AuthType::Bearer => {
-                let token = self.token
-                    .clone()
-                    .expect("using bearer auth but token missing from config!");
-                r.bearer_auth(token)
+                let token = self.session_token("bearer")?;
+                let r = r.header("authorization", &format!("Bearer {}", token));
+                sent_token = Some(token);
+                r
             }
             AuthType::Basic => {
                 let (username, password) = self.basic_auth
                     .clone()
                     .expect("using basic auth but username and password missing from config!");
-                r.basic_auth(username, Some(password))
+                let credentials = base64::encode(&format!("{}:{}", username, password));
+                r.header("authorization", &format!("Basic {}", credentials))
             }
             AuthType::JWT => {
-                let token = self.token
+                let token = self.session_token("JWT")?;
+                let r = r.header("authorization", &format!("JWT {}", token));
+                sent_token = Some(token);
+                r
+            }
+            AuthType::ApiKey { name, location } => {
+                let key = self.token
+                    .clone()
+                    .expect("using API key auth but key missing from config!");
+                match location {
+                    ApiKeyLocation::Header => r.header(name, &key),
+                    ApiKeyLocation::Query => RequestOptions::new().query(name, &key).apply(r),
+                }
+            }
+            AuthType::OAuth2ClientCredentials { token_url, scope } => {
+                let token = self.token_cache.token(|| self.fetch_token(token_url, scope.as_ref()))?;
+                let r = r.header("authorization", &format!("Bearer {}", token));
+                sent_token = Some(token);
+                r
+            }
+            AuthType::Signed { canonical_string, signature_header, timestamp_header } => {
+                let secret = self.token
                     .clone()
-                    .expect("using JWT auth but token missing from config!");
-                r.header("authorization", format!("JWT {}", token))
+                    .expect("using signed requests but secret missing from config!");
+                signing::sign(r, canonical_string, &secret, signature_header, timestamp_header)
             }
+        };
+        Ok((r, sent_token))
+    }
+
+    /// The token from logging in if the client has a login, otherwise the configured token.
+    fn session_token(&self, auth_name: &str) -> Result<String, RestClientError> {
+        match &self.login {
+            Some(login) => self.token_cache.token(|| self.log_in(login).map(|token| (token, None))),
+            None => self.token
+                .clone()
+                .ok_or_else(|| RestClientError::Auth(format!("using {} auth but token missing from config!", auth_name))),
         }
     }
 
-    fn send(&self, request_builder: RequestBuilder, payload: &Value) -> reqwest::Result<reqwest::Response> {
-        let request_builder = match &self.auth_type {
-            None => request_builder,
-            Some(auth_type) => self.apply_auth_to_request(auth_type, request_builder),
-        };
-        request_builder.json(payload).send()
+    fn log_in(&self, login: &Login) -> Result<String, RestClientError> {
+        let (username, password) = self.basic_auth
+            .clone()
+            .expect("logging in but username and password missing from config!");
+        let request = RestRequest::new(Method::Post, &self.qualify_url(&login.url))
+            .json(&login.body_for(&username, &password));
+        login.token_from(&self.dispatch(request)?)
     }
 
-    pub fn post(&self, url: &str, payload: &Value) -> reqwest::Result<reqwest::Response> {
-        let request_builder = Client::new().post(&self.qualify_url(url));
-        self.send(request_builder, payload)
+    fn fetch_token(&self, token_url: &str, scope: Option<&String>) -> Result<(String, Option<Duration>), RestClientError> {
+        let (client_id, client_secret) = self.basic_auth
+            .clone()
+            .expect("using OAuth2 auth but client id and secret missing from config!");
+        let url = if token_url.contains("://") { token_url.to_owned() } else { self.qualify_url(token_url) };
+        let request = token_request(&url, &client_id, &client_secret, scope.map(String::as_str));
+        parse_token_response(&self.dispatch(request)?)
+    }
+
+    /// Sends the request, and once more with a fresh token if a fetched token was refused.
+    fn send(&self, request: RestRequest, payload: Option<&Value>, options: &RequestOptions)
+            -> Result<RestResponse, RestClientError> {
+        let request = self.options.merged_with(options).apply(request);
+        let request = match payload {
+            None => request,
+            Some(payload) => request.json(payload),
+        };
+
+        let (response, sent_token) = self.send_authorized(request.clone())?;
+        if response.status == 401 && self.fetches_tokens() {
+            if let Some(token) = sent_token {
+                self.token_cache.invalidate(&token);
+            }
+            return self.send_authorized(request).map(|(response, _)| response);
+        }
+        Ok(response)
     }
 
-    pub fn patch(&self, url: &str, payload: &Value) -> reqwest::Result<reqwest::Response> {
-        let request_builder = reqwest::Client::new().patch(&self.qualify_url(url));
-        self.send(request_builder, payload)
+    fn fetches_tokens(&self) -> bool {
+        match &self.auth_type {
+            Some(AuthType::OAuth2ClientCredentials { .. }) => true,
+            Some(AuthType::Bearer) | Some(AuthType::JWT) => self.login.is_some(),
+            _ => false,
+        }
     }
 
-    pub fn get(&self, url: &str) -> reqwest::Result<reqwest::Response> {
-        let get_request_builder = reqwest::Client::new().get(&self.qualify_url(url));
-        let get_request_builder = match &self.auth_type {
-            None => get_request_builder,
-            Some(auth_type) => self.apply_auth_to_request(auth_type, get_request_builder),
+    /// Sends the request with the client's auth, returning the token it was sent with.
+    fn send_authorized(&self, request: RestRequest) -> Result<(RestResponse, Option<String>), RestClientError> {
+        let (request, sent_token) = match &self.auth_type {
+            None => (request, None),
+            Some(auth_type) => self.apply_auth_to_request(auth_type, request)?,
         };
+        Ok((self.dispatch(request)?, sent_token))
+    }
+
+    fn dispatch(&self, request: RestRequest) -> Result<RestResponse, RestClientError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire();
+        }
+        self.transport.send(request)
+    }
+
+    pub fn request(&self, method: Method, url: &str, payload: Option<&Value>) -> Result<RestResponse, RestClientError> {
+        self.request_with(method, url, payload, &RequestOptions::default())
+    }
+
+    /// Sends a request with `options` on top of the client's own, replacing any of the same name.
+    pub fn request_with(&self, method: Method, url: &str, payload: Option<&Value>, options: &RequestOptions)
+                        -> Result<RestResponse, RestClientError> {
+        let request = RestRequest::new(method, &self.qualify_url(url));
+        self.send(request, payload, options)
+    }
+
+    pub fn post(&self, url: &str, payload: &Value) -> Result<RestResponse, RestClientError> {
+        self.request(Method::Post, url, Some(payload))
+    }
+
+    pub fn patch(&self, url: &str, payload: &Value) -> Result<RestResponse, RestClientError> {
+        self.request(Method::Patch, url, Some(payload))
+    }
+
+    pub fn put(&self, url: &str, payload: &Value) -> Result<RestResponse, RestClientError> {
+        self.request(Method::Put, url, Some(payload))
+    }
+
+    pub fn get(&self, url: &str) -> Result<RestResponse, RestClientError> {
+        self.request(Method::Get, url, None)
+    }
 
-        get_request_builder.send()
+    pub fn delete(&self, url: &str) -> Result<RestResponse, RestClientError> {
+        self.request(Method::Delete, url, None)
+    }
+
+    pub fn head(&self, url: &str) -> Result<RestResponse, RestClientError> {
+        self.request(Method::Head, url, None)
+    }
+
+    pub fn options(&self, url: &str) -> Result<RestResponse, RestClientError> {
+        self.request(Method::Options, url, None)
     }
 }
 
@@ -105,8 +298,15 @@ mod rest_client {
     use mockito::Matcher;
     use serde_json::Value;
 
-    use crate::{AuthType, RestClient};
+    use crate::{ApiKeyLocation, AuthType, Method, RestClient, signing};
+    use crate::response::{RestClientError, RestResponse};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::transport::InMemoryTransport;
     use crate::config_builder::ClientConfigurationBuilder;
+    use crate::login::Login;
+    use crate::request_options::RequestOptions;
 
     #[test]
     fn test_basic_auth_request() {
@@ -114,7 +314,8 @@ mod rest_client {
             .basic_auth("foo", "bar")
             .auth_type(AuthType::Basic)
             .root_url(&mockito::server_url())
-            .build();
+            .build()
+            .unwrap();
 
         let header = base64::encode("foo:bar");
         let header = format!("Basic {}", header);
@@ -126,7 +327,7 @@ mod rest_client {
 
         let response: Value = config.get("/")
             .unwrap()
-            .json()
+            .json
             .expect("response is not of type JSON!");
         assert_eq!("hello", response["response"].as_str().unwrap());
     }
@@ -137,7 +338,8 @@ mod rest_client {
             .token("xyz")
             .auth_type(AuthType::Bearer)
             .root_url(&mockito::server_url())
-            .build();
+            .build()
+            .unwrap();
 
         let valid_request = mockito::mock("GET", "/")
             .match_header("authorization", "Bearer xyz")
@@ -146,7 +348,7 @@ mod rest_client {
 
         let response: Value = config.get("/")
             .unwrap()
-            .json()
+            .json
             .expect("response is not of type JSON!");
         assert_eq!("hello", response["response"].as_str().unwrap());
     }
@@ -157,7 +359,8 @@ mod rest_client {
             .token("xyz")
             .auth_type(AuthType::Bearer)
             .root_url(&mockito::server_url())
-            .build();
+            .build()
+            .unwrap();
 
         let payload: Value = json!({ "foo": "bar", "age": 123 });
 
@@ -169,8 +372,302 @@ mod rest_client {
 
         let response: Value = config.post("/", &payload)
             .unwrap()
-            .json()
+            .json
             .expect("response is not of type JSON!");
         assert_eq!("hello", response["response"].as_str().unwrap());
     }
+
+    #[test]
+    fn test_put_request() {
+        let config = ClientConfigurationBuilder::new()
+            .token("xyz")
+            .auth_type(AuthType::Bearer)
+            .root_url(&mockito::server_url())
+            .build()
+            .unwrap();
+
+        let payload: Value = json!({ "foo": "baz" });
+
+        let valid_request = mockito::mock("PUT", "/put/1")
+            .match_header("authorization", "Bearer xyz")
+            .match_body(Matcher::Json(payload.clone()))
+            .with_body(r#"{"foo": "baz"}"#)
+            .create();
+
+        let response: Value = config.put("/put/1", &payload)
+            .unwrap()
+            .json
+            .expect("response is not of type JSON!");
+        assert_eq!("baz", response["foo"].as_str().unwrap());
+        valid_request.assert();
+    }
+
+    #[test]
+    fn test_bodiless_verbs() {
+        let config = ClientConfigurationBuilder::new()
+            .basic_auth("foo", "bar")
+            .auth_type(AuthType::Basic)
+            .root_url(&mockito::server_url())
+            .build()
+            .unwrap();
+
+        let header = format!("Basic {}", base64::encode("foo:bar"));
+
+        let delete = mockito::mock("DELETE", "/verbs/1")
+            .match_header("authorization", header.as_str())
+            .with_status(204)
+            .create();
+        let head = mockito::mock("HEAD", "/verbs/1")
+            .match_header("authorization", header.as_str())
+            .with_status(200)
+            .create();
+        let options = mockito::mock("OPTIONS", "/verbs/1")
+            .match_header("authorization", header.as_str())
+            .with_header("allow", "GET, PATCH, DELETE")
+            .create();
+
+        assert_eq!(config.delete("/verbs/1").unwrap().status, 204);
+        assert_eq!(config.head("/verbs/1").unwrap().status, 200);
+
+        let response = config.options("/verbs/1").unwrap();
+        assert_eq!(response.header("allow"), Some("GET, PATCH, DELETE"));
+
+        delete.assert();
+        head.assert();
+        options.assert();
+    }
+
+    #[test]
+    fn test_generic_request() {
+        let config = ClientConfigurationBuilder::new()
+            .root_url(&mockito::server_url())
+            .build()
+            .unwrap();
+
+        let payload: Value = json!({ "foo": "bar" });
+
+        let valid_request = mockito::mock("POST", "/generic")
+            .match_body(Matcher::Json(payload.clone()))
+            .with_status(201)
+            .create();
+
+        let response = config.request(Method::Post, "/generic", Some(&payload)).unwrap();
+        assert_eq!(response.status, 201);
+        assert!(response.url.ends_with("/generic"));
+        valid_request.assert();
+    }
+
+    #[test]
+    fn test_requests_go_through_the_transport() {
+        let transport = InMemoryTransport::new()
+            .route(Method::Patch, "/castles/*", |r| RestResponse::from_json(200, &r.json_body().unwrap()));
+        let config = ClientConfigurationBuilder::new()
+            .token("xyz")
+            .auth_type(AuthType::JWT)
+            .root_url("http://castles")
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        let response = config.patch("/castles/1", &json!({ "moat": true })).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.json, Some(json!({ "moat": true })));
+        assert_eq!(response.url, "http://castles/castles/1");
+
+        assert_eq!(config.get("/moats/1").unwrap().status, 404);
+    }
+
+    #[test]
+    fn test_api_key_auth() {
+        let api_key = |location| AuthType::ApiKey { name: "X-API-Key".to_owned(), location };
+        let auth_type: AuthType = serde_json::from_value(json!({
+            "ApiKey": { "name": "X-API-Key", "location": "query" }
+        })).unwrap();
+        assert_eq!(auth_type, api_key(ApiKeyLocation::Query));
+
+        let transport = Arc::new(InMemoryTransport::new());
+        for location in vec![ApiKeyLocation::Header, ApiKeyLocation::Query] {
+            ClientConfigurationBuilder::new()
+                .token("s3cret")
+                .auth_type(api_key(location))
+                .root_url("http://castles")
+                .transport(transport.clone())
+                .build()
+                .unwrap()
+                .get("/castles")
+                .unwrap();
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].header_value("x-api-key"), Some("s3cret"));
+        assert_eq!(requests[0].url, "http://castles/castles");
+        assert_eq!(requests[1].header_value("x-api-key"), None);
+        assert_eq!(requests[1].url, "http://castles/castles?X-API-Key=s3cret");
+    }
+
+    #[test]
+    fn test_oauth2_tokens_are_cached_and_refreshed_on_401() {
+        let issued = Arc::new(AtomicUsize::new(0));
+        let issued_by_server = issued.clone();
+        let transport = InMemoryTransport::new()
+            .route(Method::Post, "/oauth/token", move |r| {
+                assert_eq!(r.header_value("authorization"), Some(format!("Basic {}", base64::encode("id:secret")).as_str()));
+                let n = issued_by_server.fetch_add(1, Ordering::SeqCst) + 1;
+                RestResponse::from_json(200, &json!({ "access_token": format!("t{}", n), "expires_in": 900 }))
+            })
+            .route(Method::Get, "/castles", |r| {
+                // The first token is revoked on the server side
+                match r.header_value("authorization") {
+                    Some("Bearer t2") => RestResponse::from_json(200, &json!([])),
+                    _ => RestResponse::from_json(401, &json!({})),
+                }
+            });
+        let config = ClientConfigurationBuilder::new()
+            .basic_auth("id", "secret")
+            .auth_type(AuthType::OAuth2ClientCredentials { token_url: "http://auth/oauth/token".to_owned(), scope: None })
+            .root_url("http://castles")
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.get("/castles").unwrap().status, 200);
+        assert_eq!(config.get("/castles").unwrap().status, 200);
+        assert_eq!(issued.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_oauth2_against_a_token_server() {
+        let token = mockito::mock("POST", "/oauth/token")
+            .match_header("authorization", format!("Basic {}", base64::encode("id:secret")).as_str())
+            .match_body("grant_type=client_credentials&scope=castles")
+            .with_body(r#"{"access_token": "abc", "token_type": "bearer", "expires_in": 900}"#)
+            .expect(1)
+            .create();
+        let castles = mockito::mock("GET", "/oauth/castles")
+            .match_header("authorization", "Bearer abc")
+            .with_body("[]")
+            .expect(2)
+            .create();
+
+        let config = ClientConfigurationBuilder::new()
+            .basic_auth("id", "secret")
+            .auth_type(AuthType::OAuth2ClientCredentials { token_url: "/oauth/token".to_owned(), scope: Some("castles".to_owned()) })
+            .root_url(&mockito::server_url())
+            .build()
+            .unwrap();
+
+        assert_eq!(config.get("/oauth/castles").unwrap().status, 200);
+        assert_eq!(config.get("/oauth/castles").unwrap().status, 200);
+        token.assert();
+        castles.assert();
+    }
+
+    #[test]
+    fn test_login_token_is_used_and_renewed_on_401() {
+        let logins = Arc::new(AtomicUsize::new(0));
+        let logins_on_server = logins.clone();
+        let transport = Arc::new(InMemoryTransport::new()
+            .route(Method::Post, "/api/token/", move |r| {
+                assert_eq!(r.json_body().unwrap(), json!({ "username": "jumbo", "password": "p:ss" }));
+                let n = logins_on_server.fetch_add(1, Ordering::SeqCst) + 1;
+                RestResponse::from_json(200, &json!({ "access": format!("t{}", n) }))
+            })
+            .route(Method::Get, "/castles", |r| match r.header_value("authorization") {
+                Some("JWT t2") => RestResponse::from_json(200, &json!([])),
+                _ => RestResponse::from_json(401, &json!({})),
+            }));
+        let login: Login = serde_json::from_value(json!({
+            "url": "/api/token/",
+            "body": { "username": "{username}", "password": "{password}" },
+            "token_pointer": "/access"
+        })).unwrap();
+        let config = ClientConfigurationBuilder::new()
+            .basic_auth("jumbo", "p:ss")
+            .auth_type(AuthType::JWT)
+            .login(login)
+            .root_url("http://castles")
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(config.get("/castles").unwrap().status, 200);
+        assert_eq!(config.get("/castles").unwrap().status, 200);
+        assert_eq!(logins.load(Ordering::SeqCst), 2);
+        assert_eq!(transport.requests().len(), 5);
+    }
+
+    #[test]
+    fn test_signed_requests_are_verified_by_the_server() {
+        let transport = Arc::new(InMemoryTransport::new()
+            .route(Method::Post, "/castles", |r| {
+                let timestamp: u64 = r.header_value("x-ts").unwrap().parse().unwrap();
+                let canonical = format!("POST /castles?dry_run=true {} {{\"moat\":true}}", timestamp);
+                let expected = signing::hmac_sha256("s3cret", &canonical);
+                if r.header_value("x-sig") == Some(expected.as_str()) {
+                    RestResponse::from_json(201, &json!({}))
+                } else {
+                    RestResponse::from_json(401, &json!({}))
+                }
+            }));
+        let auth_type: AuthType = serde_json::from_value(json!({
+            "Signed": {
+                "canonical_string": "{method} {path} {timestamp} {body}",
+                "signature_header": "X-Sig",
+                "timestamp_header": "X-Ts"
+            }
+        })).unwrap();
+        let config = ClientConfigurationBuilder::new()
+            .token("s3cret")
+            .auth_type(auth_type)
+            .options(RequestOptions::new().query("dry_run", "true"))
+            .root_url("http://castles")
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(config.post("/castles", &json!({ "moat": true })).unwrap().status, 201);
+        let body = transport.requests()[0].body.clone().unwrap();
+        assert_eq!(body, br#"{"moat":true}"#.to_vec());
+
+        let defaults: AuthType = serde_json::from_value(json!({ "Signed": {} })).unwrap();
+        assert_eq!(defaults, AuthType::Signed {
+            canonical_string: signing::default_canonical_string(),
+            signature_header: "X-Signature".to_owned(),
+            timestamp_header: "X-Timestamp".to_owned(),
+        });
+    }
+
+    #[test]
+    fn test_request_options() {
+        let transport = Arc::new(InMemoryTransport::new());
+        let config = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .options(RequestOptions::new().header("X-Tenant-Id", "7").query("dry_run", "false"))
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        let options = RequestOptions::new().query("dry_run", "true").cookie("theme", "dark");
+        config.request_with(Method::Get, "/castles", None, &options).unwrap();
+        config.get("/moats").unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "http://castles/castles?dry_run=true");
+        assert_eq!(requests[0].header_value("cookie"), Some("theme=dark"));
+        assert_eq!(requests[1].url, "http://castles/moats?dry_run=false");
+        assert_eq!(requests[1].header_value("x-tenant-id"), Some("7"));
+    }
+
+    #[test]
+    fn test_connection_errors_are_classified() {
+        let config = ClientConfigurationBuilder::new()
+            .root_url("http://127.0.0.1:1")
+            .build()
+            .unwrap();
+
+        match config.get("/") {
+            Err(RestClientError::Connect(_)) => {}
+            other => panic!("expected a connect error, got {:?}", other),
+        }
+    }
 }
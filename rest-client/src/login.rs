@@ -0,0 +1,77 @@
+use serde_json::Value;
+
+use crate::response::{RestClientError, RestResponse};
+
+/// Logs in by posting credentials to `url` and reading the token found at `token_pointer`,
+/// a JSON pointer such as `/access`, in the response.
+///
+/// `{username}` and `{password}` in the string values of `body` are replaced by the credentials.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Login {
+    pub url: String,
+    pub body: Value,
+    pub token_pointer: String,
+}
+
+impl Login {
+    pub fn body_for(&self, username: &str, password: &str) -> Value {
+        fill_credentials(&self.body, username, password)
+    }
+
+    pub fn token_from(&self, response: &RestResponse) -> Result<String, RestClientError> {
+        if !response.is_success() {
+            return Err(RestClientError::Auth(format!("login at {} returned {}", response.url, response.status)));
+        }
+        response.json.as_ref()
+            .and_then(|body| body.pointer(&self.token_pointer))
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| RestClientError::Auth(
+                format!("no token at {} in the login response from {}", self.token_pointer, response.url)
+            ))
+    }
+}
+
+fn fill_credentials(template: &Value, username: &str, password: &str) -> Value {
+    match template {
+        Value::String(s) => Value::from(s.replace("{username}", username).replace("{password}", password)),
+        Value::Array(values) => values.iter().map(|v| fill_credentials(v, username, password)).collect(),
+        Value::Object(fields) => fields.iter()
+            .map(|(name, v)| (name.clone(), fill_credentials(v, username, password)))
+            .collect(),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod login {
+    use super::*;
+
+    fn login() -> Login {
+        serde_json::from_value(json!({
+            "url": "/api/token/",
+            "body": { "username": "{username}", "password": "{password}", "remember": true },
+            "token_pointer": "/data/access"
+        })).unwrap()
+    }
+
+    #[test]
+    fn credentials_are_filled_into_the_body() {
+        assert_eq!(login().body_for("jumbo", "p:ss"), json!({
+            "username": "jumbo",
+            "password": "p:ss",
+            "remember": true
+        }));
+    }
+
+    #[test]
+    fn token_is_read_from_the_response() {
+        let response = RestResponse::from_json(200, &json!({ "data": { "access": "abc" } }));
+        assert_eq!(login().token_from(&response), Ok("abc".to_owned()));
+
+        let response = RestResponse::from_json(200, &json!({ "access": "abc" }));
+        assert!(login().token_from(&response).is_err());
+        let response = RestResponse::from_json(401, &json!({}));
+        assert!(login().token_from(&response).is_err());
+    }
+}
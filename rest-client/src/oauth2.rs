@@ -0,0 +1,125 @@
+use std::fmt::{Debug, Error, Formatter};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::Method;
+use crate::response::{RestClientError, RestResponse};
+use crate::transport::RestRequest;
+
+/// Tokens are refreshed this long before they expire, or halfway through their lifetime if shorter.
+const REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+struct CachedToken {
+    token: String,
+    refresh_at: Option<Instant>,
+}
+
+/// Holds the current access token, shared between every thread sending through a client.
+#[derive(Default)]
+pub(crate) struct TokenCache {
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl TokenCache {
+    /// The cached token, or a new one from `fetch` if there is none or it is about to expire.
+    /// Other threads wait while a token is fetched instead of fetching their own.
+    pub(crate) fn token<F>(&self, fetch: F) -> Result<String, RestClientError>
+        where F: FnOnce() -> Result<(String, Option<Duration>), RestClientError> {
+        let mut cached = self.cached.lock().unwrap();
+        if let Some(token) = cached.as_ref() {
+            if token.refresh_at.map_or(true, |at| Instant::now() < at) {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let (token, expires_in) = fetch()?;
+        let refresh_at = expires_in.map(|lifetime| Instant::now() + lifetime - REFRESH_MARGIN.min(lifetime / 2));
+        *cached = Some(CachedToken { token: token.clone(), refresh_at });
+        Ok(token)
+    }
+
+    /// Drops the `refused` token, unless another thread has already replaced it with a new one.
+    pub(crate) fn invalidate(&self, refused: &str) {
+        let mut cached = self.cached.lock().unwrap();
+        if cached.as_ref().map(|c| c.token.as_str()) == Some(refused) {
+            *cached = None;
+        }
+    }
+}
+
+impl Debug for TokenCache {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let cached = self.cached.lock().unwrap();
+        f.debug_struct("TokenCache")
+            .field("cached", &cached.is_some())
+            .field("refresh_at", &cached.as_ref().and_then(|c| c.refresh_at))
+            .finish()
+    }
+}
+
+/// A client credentials grant request, authenticating the client with HTTP basic auth.
+pub(crate) fn token_request(token_url: &str, client_id: &str, client_secret: &str, scope: Option<&str>) -> RestRequest {
+    let mut form = vec![("grant_type", "client_credentials")];
+    if let Some(scope) = scope {
+        form.push(("scope", scope));
+    }
+    let credentials = base64::encode(&format!("{}:{}", client_id, client_secret));
+
+    let mut request = RestRequest::new(Method::Post, token_url)
+        .header("authorization", &format!("Basic {}", credentials))
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("accept", "application/json");
+    request.body = Some(serde_urlencoded::to_string(&form).unwrap().into_bytes());
+    request
+}
+
+/// The access token and its lifetime from a token endpoint response.
+pub(crate) fn parse_token_response(response: &RestResponse) -> Result<(String, Option<Duration>), RestClientError> {
+    if !response.is_success() {
+        return Err(RestClientError::Auth(
+            format!("token endpoint {} returned {}: {}", response.url, response.status, response.text())
+        ));
+    }
+
+    let body = response.json.as_ref()
+        .ok_or_else(|| RestClientError::Auth(format!("token endpoint {} did not return JSON", response.url)))?;
+    let token = body["access_token"].as_str()
+        .ok_or_else(|| RestClientError::Auth(format!("no access_token from token endpoint {}", response.url)))?;
+    let expires_in = body["expires_in"].as_u64().map(Duration::from_secs);
+    Ok((token.to_owned(), expires_in))
+}
+
+#[cfg(test)]
+mod token_cache {
+    use super::*;
+
+    #[test]
+    fn tokens_are_cached_until_they_are_about_to_expire() {
+        let cache = TokenCache::default();
+        let fetch = |token: &str, seconds: Option<u64>| {
+            let token = token.to_owned();
+            move || Ok((token, seconds.map(Duration::from_secs)))
+        };
+
+        assert_eq!(cache.token(fetch("a", Some(900))).unwrap(), "a");
+        assert_eq!(cache.token(fetch("b", Some(900))).unwrap(), "a");
+
+        cache.invalidate("stale");
+        assert_eq!(cache.token(fetch("b", Some(900))).unwrap(), "a");
+        cache.invalidate("a");
+        assert_eq!(cache.token(fetch("c", Some(0))).unwrap(), "c");
+        assert_eq!(cache.token(fetch("d", None)).unwrap(), "d");
+        assert_eq!(cache.token(fetch("e", None)).unwrap(), "d");
+        assert_eq!(format!("{:?}", cache), "TokenCache { cached: true, refresh_at: None }");
+    }
+
+    #[test]
+    fn token_requests_carry_the_client_credentials() {
+        let request = token_request("http://auth/token", "vaccine", "s3cret", Some("castles.write"));
+        assert_eq!(request.header_value("authorization"), Some("Basic dmFjY2luZTpzM2NyZXQ="));
+        assert_eq!(request.body, Some(b"grant_type=client_credentials&scope=castles.write".to_vec()));
+
+        let error = parse_token_response(&RestResponse::from_json(401, &json!({}))).unwrap_err();
+        assert!(error.to_string().starts_with("authentication failed: token endpoint  returned 401"));
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A token bucket shared by every thread sending through a client. Tokens refill continuously at
+/// `per_second`, and up to `burst` of them can be saved up while the client is idle.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: f64, burst: usize) -> Self {
+        assert!(per_second > 0.0, "rate limit must be positive, got {}", per_second);
+        let burst = burst.max(1) as f64;
+        RateLimiter {
+            per_second,
+            burst,
+            bucket: Mutex::new(Bucket { tokens: burst, refilled_at: Instant::now() }),
+        }
+    }
+
+    /// Takes a token, sleeping until one is available.
+    pub fn acquire(&self) {
+        while let Some(wait) = self.try_acquire() {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Takes a token if one is available, otherwise returns how long until the next one is.
+    fn try_acquire(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.refilled_at);
+        let refill = refill.as_secs() as f64 + f64::from(refill.subsec_nanos()) / 1e9;
+        bucket.tokens = (bucket.tokens + refill * self.per_second).min(self.burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            let seconds = (1.0 - bucket.tokens) / self.per_second;
+            Some(Duration::from_micros((seconds * 1e6).ceil() as u64))
+        }
+    }
+}
+
+#[cfg(test)]
+mod rate_limiter {
+    use super::*;
+
+    #[test]
+    fn bursts_are_allowed_then_requests_are_spaced_out() {
+        let limiter = RateLimiter::new(20.0, 3);
+        for _ in 0..3 {
+            assert_eq!(limiter.try_acquire(), None);
+        }
+        assert!(limiter.try_acquire().is_some());
+
+        let started = Instant::now();
+        limiter.acquire();
+        limiter.acquire();
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+}
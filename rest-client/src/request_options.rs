@@ -0,0 +1,130 @@
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::transport::RestRequest;
+
+/// Extra headers, query parameters and cookies sent along with a request.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestOptions {
+    pub headers: Vec<(String, String)>,
+    pub query: Vec<(String, String)>,
+    pub cookies: Vec<(String, String)>,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        RequestOptions::default()
+    }
+
+    /// Header names are lowercased, so that a header set twice is only sent once.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        set(&mut self.headers, &name.to_lowercase(), value);
+        self
+    }
+
+    pub fn query(mut self, name: &str, value: &str) -> Self {
+        set(&mut self.query, name, value);
+        self
+    }
+
+    pub fn cookie(mut self, name: &str, value: &str) -> Self {
+        set(&mut self.cookies, name, value);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.query.is_empty() && self.cookies.is_empty()
+    }
+
+    /// These options with any of the same name in `other` replaced by those of `other`.
+    pub fn merged_with(&self, other: &RequestOptions) -> RequestOptions {
+        let mut merged = self.clone();
+        for (name, value) in &other.headers {
+            set(&mut merged.headers, &name.to_lowercase(), value);
+        }
+        for (name, value) in &other.query {
+            set(&mut merged.query, name, value);
+        }
+        for (name, value) in &other.cookies {
+            set(&mut merged.cookies, name, value);
+        }
+        merged
+    }
+
+    pub fn to_json(&self) -> Value {
+        let pairs = |pairs: &[(String, String)]| -> Value {
+            pairs.iter().map(|(name, value)| (name.clone(), Value::from(value.as_str()))).collect()
+        };
+        json!({
+            "headers": pairs(&self.headers),
+            "query": pairs(&self.query),
+            "cookies": pairs(&self.cookies),
+        })
+    }
+
+    pub(crate) fn apply(&self, mut request: RestRequest) -> RestRequest {
+        if !self.query.is_empty() {
+            let mut url = Url::parse(&request.url).expect(&format!("invalid url {}", request.url));
+            url.query_pairs_mut().extend_pairs(&self.query);
+            request.url = url.into_string();
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if !self.cookies.is_empty() {
+            let cookies: Vec<String> = self.cookies.iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            request = request.header("cookie", &cookies.join("; "));
+        }
+        request
+    }
+}
+
+fn set(pairs: &mut Vec<(String, String)>, name: &str, value: &str) {
+    match pairs.iter_mut().find(|(n, _)| n == name) {
+        Some(pair) => pair.1 = value.to_owned(),
+        None => pairs.push((name.to_owned(), value.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod request_options {
+    use crate::Method;
+
+    use super::*;
+
+    #[test]
+    fn options_are_merged_by_name() {
+        let session = RequestOptions::new()
+            .header("X-Tenant-Id", "7")
+            .header("X-Api-Version", "1")
+            .query("dry_run", "false");
+        let endpoint = RequestOptions::new()
+            .header("x-api-version", "2")
+            .cookie("theme", "dark");
+
+        let merged = session.merged_with(&endpoint);
+        assert_eq!(merged.headers, vec![
+            ("x-tenant-id".to_owned(), "7".to_owned()),
+            ("x-api-version".to_owned(), "2".to_owned()),
+        ]);
+        assert_eq!(merged.query, vec![("dry_run".to_owned(), "false".to_owned())]);
+        assert_eq!(merged.to_json()["cookies"], json!({ "theme": "dark" }));
+    }
+
+    #[test]
+    fn options_are_applied_to_requests() {
+        let options = RequestOptions::new()
+            .header("X-Tenant-Id", "7")
+            .query("dry_run", "false")
+            .query("q", "a b")
+            .cookie("a", "1")
+            .cookie("b", "2");
+        let request = options.apply(RestRequest::new(Method::Get, "http://x/castles?page=2"));
+
+        assert_eq!(request.url, "http://x/castles?page=2&dry_run=false&q=a+b");
+        assert_eq!(request.header_value("x-tenant-id"), Some("7"));
+        assert_eq!(request.header_value("cookie"), Some("a=1; b=2"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::error::Error as StdError;
+use std::fmt::{Display, Error, Formatter};
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// What came back for a request, read in full.
+#[derive(Debug, Clone)]
+pub struct RestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub json: Option<Value>,
+    pub elapsed: Duration,
+    pub url: String,
+}
+
+impl RestResponse {
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: Vec<u8>, elapsed: Duration, url: &str) -> Self {
+        let json = serde_json::from_slice(&body).ok();
+        RestResponse { status, headers, body, json, elapsed, url: url.to_owned() }
+    }
+
+    /// A response carrying `body` as JSON, mostly useful for stand-in transports.
+    pub fn from_json(status: u16, body: &Value) -> Self {
+        let headers = vec![("content-type".to_owned(), "application/json".to_owned())];
+        let body = serde_json::to_vec(body).expect("failed to serialize body");
+        RestResponse::new(status, headers, body, Duration::from_millis(0), "")
+    }
+
+    /// Reads the whole body, giving up once `total_timeout` has passed since `started`. The check is
+    /// made between reads, each of which is bounded by the client's read timeout.
+    pub(crate) fn from_reqwest(mut response: reqwest::Response, started: Instant, total_timeout: Option<Duration>)
+                               -> Result<Self, RestClientError> {
+        let url = response.url().as_str().to_owned();
+        let timed_out = || match total_timeout {
+            Some(total) if started.elapsed() > total => Err(RestClientError::Timeout(
+                format!("{} took longer than {:?}", url, total)
+            )),
+            _ => Ok(()),
+        };
+        timed_out()?;
+
+        let headers = response.headers()
+            .iter()
+            .map(|(name, value)| (name.as_str().to_owned(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect();
+
+        let mut body = vec![];
+        let mut chunk = [0; 8192];
+        loop {
+            let read = response.read(&mut chunk).map_err(|e| match e.kind() {
+                io::ErrorKind::TimedOut => RestClientError::Timeout(format!("reading {}: {}", url, e)),
+                _ => RestClientError::Decode(e.to_string()),
+            })?;
+            if read == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..read]);
+            timed_out()?;
+        }
+
+        Ok(RestResponse::new(response.status().as_u16(), headers, body, started.elapsed(), &url))
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        self.status >= 500 && self.status < 600
+    }
+
+    /// The first value of the header `name`, which is matched case insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestClientError {
+    Connect(String),
+    Timeout(String),
+    Tls(String),
+    Decode(String),
+    Auth(String),
+    Other(String),
+}
+
+impl RestClientError {
+    pub fn message(&self) -> &str {
+        match self {
+            RestClientError::Connect(m)
+            | RestClientError::Timeout(m)
+            | RestClientError::Tls(m)
+            | RestClientError::Decode(m)
+            | RestClientError::Auth(m)
+            | RestClientError::Other(m) => m,
+        }
+    }
+}
+
+impl Display for RestClientError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let kind = match self {
+            RestClientError::Connect(_) => "connection failed",
+            RestClientError::Timeout(_) => "timed out",
+            RestClientError::Tls(_) => "TLS error",
+            RestClientError::Decode(_) => "failed to decode response",
+            RestClientError::Auth(_) => "authentication failed",
+            RestClientError::Other(_) => "request failed",
+        };
+        write!(f, "{}: {}", kind, self.message())
+    }
+}
+
+impl StdError for RestClientError {}
+
+impl From<reqwest::Error> for RestClientError {
+    fn from(e: reqwest::Error) -> Self {
+        let message = e.to_string();
+        if e.is_timeout() {
+            RestClientError::Timeout(message)
+        } else if e.is_serialization() {
+            RestClientError::Decode(message)
+        } else if is_tls_error(&e) {
+            RestClientError::Tls(message)
+        } else if e.is_http() {
+            // Hyper errors surface before any response arrived
+            RestClientError::Connect(message)
+        } else {
+            RestClientError::Other(message)
+        }
+    }
+}
+
+fn is_tls_error(e: &reqwest::Error) -> bool {
+    let mut source: Option<&(dyn StdError + 'static)> = e.get_ref().map(|e| e as &(dyn StdError + 'static));
+    while let Some(error) = source {
+        let description = error.to_string().to_lowercase();
+        if description.contains("certificate") || description.contains("tls") || description.contains("ssl") {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod rest_response {
+    use super::*;
+
+    #[test]
+    fn body_is_parsed_as_json_when_possible() {
+        let r = RestResponse::new(201, vec![], br#"{"id": 1}"#.to_vec(), Duration::from_millis(3), "http://x/a");
+        assert_eq!(r.json, Some(json!({"id": 1})));
+        assert!(r.is_success());
+        assert!(!r.is_server_error());
+
+        let r = RestResponse::new(502, vec![], b"bad gateway".to_vec(), Duration::from_millis(3), "http://x/a");
+        assert_eq!(r.json, None);
+        assert_eq!(r.text(), "bad gateway");
+        assert!(r.is_server_error());
+    }
+
+    #[test]
+    fn headers_are_matched_case_insensitively() {
+        let headers = vec![("content-type".to_owned(), "application/json".to_owned())];
+        let r = RestResponse::new(200, headers, vec![], Duration::from_millis(0), "http://x/a");
+        assert_eq!(r.header("Content-Type"), Some("application/json"));
+        assert_eq!(r.header("allow"), None);
+    }
+
+    #[test]
+    fn errors_describe_their_kind() {
+        let e = RestClientError::Timeout("operation timed out".to_owned());
+        assert_eq!(e.to_string(), "timed out: operation timed out");
+        assert_eq!(e.message(), "operation timed out");
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::transport::RestRequest;
+
+pub fn default_canonical_string() -> String {
+    "{method}\n{path}\n{timestamp}\n{body}".to_owned()
+}
+
+pub fn default_signature_header() -> String {
+    "X-Signature".to_owned()
+}
+
+pub fn default_timestamp_header() -> String {
+    "X-Timestamp".to_owned()
+}
+
+/// Fills `template` from the request: `{method}`, `{path}` (with the query string), `{timestamp}`
+/// in seconds since the epoch, `{body}` as sent and `{body_sha256}` as lowercase hex.
+pub fn canonical_string(template: &str, request: &RestRequest, timestamp: u64) -> String {
+    let body = request.body.as_ref().map(|b| b.as_slice()).unwrap_or(&[]);
+    template
+        .replace("{method}", request.method.as_str())
+        .replace("{path}", request.path())
+        .replace("{timestamp}", &timestamp.to_string())
+        .replace("{body_sha256}", &hex::encode(Sha256::digest(body)))
+        .replace("{body}", &String::from_utf8_lossy(body))
+}
+
+/// The lowercase hex HMAC-SHA256 of `message` keyed with `secret`.
+pub fn hmac_sha256(secret: &str, message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.input(message.as_bytes());
+    hex::encode(mac.result().code())
+}
+
+/// Adds the timestamp and the signature over the canonical string to the request.
+pub(crate) fn sign(request: RestRequest, template: &str, secret: &str, signature_header: &str, timestamp_header: &str)
+                   -> RestRequest {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let signature = hmac_sha256(secret, &canonical_string(template, &request, timestamp));
+    request
+        .header(timestamp_header, &timestamp.to_string())
+        .header(signature_header, &signature)
+}
+
+#[cfg(test)]
+mod signing {
+    use crate::Method;
+
+    use super::*;
+
+    #[test]
+    fn known_hmac() {
+        assert_eq!(hmac_sha256("key", "The quick brown fox jumps over the lazy dog"),
+                   "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+    }
+
+    #[test]
+    fn canonical_strings_are_filled_from_the_request() {
+        let request = RestRequest::new(Method::Patch, "http://x/castles/1?dry_run=true").json(&json!({"a": 1}));
+        assert_eq!(canonical_string(&default_canonical_string(), &request, 1560000000),
+                   "PATCH\n/castles/1?dry_run=true\n1560000000\n{\"a\":1}");
+        assert_eq!(canonical_string("{body_sha256}", &RestRequest::new(Method::Get, "http://x/"), 0),
+                   "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+}
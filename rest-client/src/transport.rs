@@ -0,0 +1,618 @@
+use std::fmt::{Debug, Error, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::{Certificate, Client, ClientBuilder, Identity};
+use serde_json::Value;
+
+use crate::Method;
+use crate::response::{RestClientError, RestResponse};
+
+/// A request as handed to a transport, with its url qualified and auth already applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl RestRequest {
+    pub fn new(method: Method, url: &str) -> Self {
+        RestRequest { method, url: url.to_owned(), headers: vec![], body: None }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_lowercase(), value.to_owned()));
+        self
+    }
+
+    pub fn json(self, payload: &Value) -> Self {
+        let mut request = self.header("content-type", "application/json");
+        request.body = Some(serde_json::to_vec(payload).expect("failed to serialize payload"));
+        request
+    }
+
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The url without its scheme and host.
+    pub fn path(&self) -> &str {
+        let without_scheme = self.url.split_once("://").map(|(_, rest)| rest).unwrap_or(&self.url);
+        without_scheme.find('/').map(|i| &without_scheme[i..]).unwrap_or("/")
+    }
+
+    pub fn json_body(&self) -> Option<Value> {
+        self.body.as_ref().and_then(|b| serde_json::from_slice(b).ok())
+    }
+}
+
+/// Carries requests to a server and brings back the responses.
+pub trait Transport: Debug + Send + Sync {
+    fn send(&self, request: RestRequest) -> Result<RestResponse, RestClientError>;
+}
+
+/// Lets a transport be shared with a client and still be inspected, e.g. for its recorded requests.
+impl<T: Transport> Transport for Arc<T> {
+    fn send(&self, request: RestRequest) -> Result<RestResponse, RestClientError> {
+        (**self).send(request)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpVersion {
+    Http1,
+    Http2,
+}
+
+/// How the shared HTTP client connects, pools connections and gives up on slow servers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSettings {
+    pub connect_timeout: Option<Duration>,
+    /// Bounds the wait for the response and for each read of its body.
+    pub read_timeout: Option<Duration>,
+    /// Bounds the whole request, from connecting to reading the last of the body.
+    pub total_timeout: Option<Duration>,
+    /// Idle connections kept per host, unbounded when `None`.
+    pub pool_size: Option<usize>,
+    pub keep_alive: bool,
+    pub http_version: HttpVersion,
+    pub tls: TlsSettings,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            connect_timeout: Some(Duration::from_secs(10)),
+            read_timeout: Some(Duration::from_secs(30)),
+            total_timeout: None,
+            pool_size: None,
+            keep_alive: true,
+            http_version: HttpVersion::Http1,
+            tls: TlsSettings::default(),
+        }
+    }
+}
+
+/// The certificate and key presented to servers that ask for a client certificate.
+#[derive(Clone, PartialEq)]
+pub enum ClientIdentity {
+    /// A PEM certificate, optionally followed by its chain, and a PEM private key.
+    Pem { cert: PathBuf, key: PathBuf },
+    /// A PKCS#12 archive holding both the certificate and the key.
+    Pkcs12 { path: PathBuf, password: String },
+}
+
+impl Debug for ClientIdentity {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            ClientIdentity::Pem { cert, key } => f.debug_struct("Pem")
+                .field("cert", cert)
+                .field("key", key)
+                .finish(),
+            ClientIdentity::Pkcs12 { path, .. } => f.debug_struct("Pkcs12")
+                .field("path", path)
+                .finish(),
+        }
+    }
+}
+
+/// Which servers are trusted and how the client identifies itself to them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsSettings {
+    /// PEM certificates trusted on top of the system roots.
+    pub ca_bundle: Option<PathBuf>,
+    pub identity: Option<ClientIdentity>,
+    /// Accepts any server certificate, including expired, self-signed and mismatched ones.
+    pub insecure_skip_verify: bool,
+}
+
+/// Sends requests over the network with one pooled reqwest client.
+#[derive(Debug)]
+pub struct ReqwestTransport {
+    client: Client,
+    total_timeout: Option<Duration>,
+    keep_alive: bool,
+}
+
+impl ReqwestTransport {
+    /// Fails when the CA bundle or client identity in the TLS settings can not be read or loaded.
+    pub fn new(settings: &HttpSettings) -> Result<Self, RestClientError> {
+        // reqwest's timeout bounds the wait for the response and then each read of the body, so it
+        // is never allowed to outlast the whole request
+        let wait_timeout = match (settings.read_timeout, settings.total_timeout) {
+            (Some(read), Some(total)) => Some(read.min(total)),
+            (read, total) => read.or(total),
+        };
+        let mut builder = ClientBuilder::new()
+            .timeout(wait_timeout)
+            .connect_timeout(settings.connect_timeout);
+
+        if !settings.keep_alive {
+            builder = builder.max_idle_per_host(0);
+        } else if let Some(pool_size) = settings.pool_size {
+            builder = builder.max_idle_per_host(pool_size);
+        }
+        if settings.http_version == HttpVersion::Http2 {
+            builder = builder.h2_prior_knowledge();
+        }
+        builder = configure_tls(builder, &settings.tls)?;
+
+        let client = builder.build()?;
+        Ok(ReqwestTransport { client, total_timeout: settings.total_timeout, keep_alive: settings.keep_alive })
+    }
+}
+
+fn configure_tls(mut builder: ClientBuilder, tls: &TlsSettings) -> Result<ClientBuilder, RestClientError> {
+    if let Some(path) = &tls.ca_bundle {
+        let certificates = split_pem_certificates(&read(path)?);
+        if certificates.is_empty() {
+            return Err(RestClientError::Tls(format!("no certificates found in {}", path.display())));
+        }
+        for pem in certificates {
+            let certificate = Certificate::from_pem(pem.as_bytes())
+                .map_err(|e| RestClientError::Tls(format!("invalid certificate in {}: {}", path.display(), e)))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    match &tls.identity {
+        Some(ClientIdentity::Pem { cert, key }) => {
+            let der = pem_to_pkcs12(&read(cert)?, &read(key)?).map_err(|e| RestClientError::Tls(format!(
+                "invalid client certificate {} or key {}: {}", cert.display(), key.display(), e
+            )))?;
+            let identity = Identity::from_pkcs12_der(&der, "")
+                .map_err(|e| RestClientError::Tls(format!("invalid client certificate {}: {}", cert.display(), e)))?;
+            builder = builder.identity(identity);
+        }
+        Some(ClientIdentity::Pkcs12 { path, password }) => {
+            let identity = Identity::from_pkcs12_der(&read(path)?, password).map_err(|e| RestClientError::Tls(
+                format!("invalid PKCS#12 archive {} or wrong password: {}", path.display(), e)
+            ))?;
+            builder = builder.identity(identity);
+        }
+        None => {}
+    }
+
+    if tls.insecure_skip_verify {
+        eprintln!("{}", INSECURE_WARNING);
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+const INSECURE_WARNING: &str = "\
+********************************************************************************
+WARNING: TLS certificate verification is DISABLED.
+Any server certificate is accepted, so traffic can be intercepted or tampered
+with. Only use --insecure against test servers you control.
+********************************************************************************";
+
+fn read(path: &Path) -> Result<Vec<u8>, RestClientError> {
+    fs::read(path).map_err(|e| RestClientError::Tls(format!("failed to read {}: {}", path.display(), e)))
+}
+
+/// Each certificate in a PEM bundle, which may also hold comments between them.
+fn split_pem_certificates(bundle: &[u8]) -> Vec<String> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let text = String::from_utf8_lossy(bundle);
+    let mut certificates = Vec::new();
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find(BEGIN) {
+        match rest[start..].find(END) {
+            Some(end) => {
+                let end = start + end + END.len();
+                certificates.push(format!("{}\n", &rest[start..end]));
+                rest = &rest[end..];
+            }
+            None => break,
+        }
+    }
+    certificates
+}
+
+/// reqwest only loads client identities from PKCS#12, so PEM files are repackaged without a password.
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "ios")))]
+fn pem_to_pkcs12(cert: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+    use openssl::pkcs12::Pkcs12;
+    use openssl::pkey::PKey;
+    use openssl::stack::Stack;
+    use openssl::x509::X509;
+
+    let mut chain = X509::stack_from_pem(cert).map_err(|e| e.to_string())?.into_iter();
+    let leaf = chain.next().ok_or("no certificate found")?;
+    let key = PKey::private_key_from_pem(key).map_err(|e| e.to_string())?;
+
+    let mut ca = Stack::new().map_err(|e| e.to_string())?;
+    for certificate in chain {
+        ca.push(certificate).map_err(|e| e.to_string())?;
+    }
+    Pkcs12::builder()
+        .name("vaccine")
+        .pkey(&key)
+        .cert(&leaf)
+        .ca(ca)
+        .build2("")
+        .and_then(|archive| archive.to_der())
+        .map_err(|e| e.to_string())
+}
+
+/// The TLS of Windows and Apple's platforms is not OpenSSL, which is not pulled in just to read PEM keys.
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "ios"))]
+fn pem_to_pkcs12(_cert: &[u8], _key: &[u8]) -> Result<Vec<u8>, String> {
+    Err("PEM client certificates are not supported on this platform, use a PKCS#12 archive instead".to_owned())
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        ReqwestTransport::new(&HttpSettings::default()).expect("failed to build the HTTP client")
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: RestRequest) -> Result<RestResponse, RestClientError> {
+        let mut request_builder = self.client.request(request.method.to_reqwest(), &request.url);
+        for (name, value) in &request.headers {
+            request_builder = request_builder.header(name.as_str(), value.as_str());
+        }
+        if let Some(body) = request.body {
+            request_builder = request_builder.body(body);
+        }
+
+        if !self.keep_alive {
+            request_builder = request_builder.header("connection", "close");
+        }
+
+        let started = Instant::now();
+        RestResponse::from_reqwest(request_builder.send()?, started, self.total_timeout)
+    }
+}
+
+type Handler = Box<dyn Fn(&RestRequest) -> RestResponse + Send + Sync>;
+
+/// Routes requests to closures instead of the network, so that schemas and oracles can be
+/// tested without a server. Unrouted requests get a 404.
+#[derive(Default)]
+pub struct InMemoryTransport {
+    routes: Vec<(Method, String, Handler)>,
+    requests: Mutex<Vec<RestRequest>>,
+}
+
+impl InMemoryTransport {
+    pub fn new() -> Self {
+        InMemoryTransport::default()
+    }
+
+    /// Routes requests for `path` to `handler`. A `*` in the path matches any single segment.
+    pub fn route<F>(mut self, method: Method, path: &str, handler: F) -> Self
+        where F: Fn(&RestRequest) -> RestResponse + Send + Sync + 'static {
+        self.routes.push((method, path.to_owned(), Box::new(handler)));
+        self
+    }
+
+    /// Every request sent so far, in order.
+    pub fn requests(&self) -> Vec<RestRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Debug for InMemoryTransport {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let routes: Vec<String> = self.routes.iter()
+            .map(|(method, path, _)| format!("{} {}", method.as_str(), path))
+            .collect();
+        f.debug_struct("InMemoryTransport").field("routes", &routes).finish()
+    }
+}
+
+impl Transport for InMemoryTransport {
+    fn send(&self, request: RestRequest) -> Result<RestResponse, RestClientError> {
+        self.requests.lock().unwrap().push(request.clone());
+
+        let handler = self.routes.iter()
+            .find(|(method, path, _)| *method == request.method && path_matches(path, request.path()))
+            .map(|(_, _, handler)| handler);
+
+        let mut response = match handler {
+            Some(handler) => handler(&request),
+            None => RestResponse::new(404, vec![], vec![], Duration::from_millis(0), ""),
+        };
+        response.url = request.url.clone();
+        Ok(response)
+    }
+}
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let path = path.split('?').next().unwrap();
+    let pattern: Vec<&str> = pattern.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    pattern.len() == path.len() && pattern.iter().zip(&path).all(|(a, b)| *a == "*" || a == b)
+}
+
+#[cfg(test)]
+mod reqwest_transport {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use super::*;
+
+    /// Answers one request with a 100 byte body, leaving `write_body` to send it.
+    fn serve<F: FnOnce(&mut TcpStream) + Send + 'static>(write_body: F) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0; 1024];
+            let _ = stream.read(&mut buffer);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n");
+            write_body(&mut stream);
+        });
+        url
+    }
+
+    fn send(settings: HttpSettings, url: &str) -> Result<RestResponse, RestClientError> {
+        ReqwestTransport::new(&HttpSettings { keep_alive: false, ..settings })
+            .unwrap()
+            .send(RestRequest::new(Method::Get, url))
+    }
+
+    #[test]
+    fn hung_requests_time_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0; 1024];
+            let _ = stream.read(&mut buffer);
+            thread::sleep(Duration::from_secs(2));
+        });
+
+        let settings = HttpSettings { total_timeout: Some(Duration::from_millis(200)), ..HttpSettings::default() };
+        match ReqwestTransport::new(&settings).unwrap().send(RestRequest::new(Method::Get, &url)) {
+            Err(RestClientError::Timeout(_)) => {}
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stalled_bodies_hit_the_read_timeout() {
+        let url = serve(|stream| {
+            let _ = stream.write_all(&[b'x'; 10]);
+            thread::sleep(Duration::from_secs(2));
+        });
+        let settings = HttpSettings { read_timeout: Some(Duration::from_millis(200)), ..HttpSettings::default() };
+        let started = Instant::now();
+        match send(settings, &url) {
+            Err(RestClientError::Timeout(_)) => {}
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn trickled_bodies_hit_the_total_timeout() {
+        let url = serve(|stream| {
+            for _ in 0..100 {
+                if stream.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+        let settings = HttpSettings {
+            read_timeout: Some(Duration::from_secs(1)),
+            total_timeout: Some(Duration::from_millis(300)),
+            ..HttpSettings::default()
+        };
+        match send(settings, &url) {
+            Err(RestClientError::Timeout(message)) => assert!(message.contains("took longer than"), "{}", message),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+
+        let url = serve(|stream| { let _ = stream.write_all(&[b'x'; 100]); });
+        assert_eq!(send(HttpSettings::default(), &url).unwrap().body.len(), 100);
+    }
+}
+
+#[cfg(all(test, not(any(target_os = "windows", target_os = "macos", target_os = "ios"))))]
+mod tls {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Rsa;
+    use openssl::ssl::{SslAcceptor, SslMethod, SslVerifyMode};
+    use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+    use openssl::x509::{X509, X509NameBuilder};
+
+    use super::*;
+
+    fn certificate(name: &str, issuer: Option<(&X509, &PKey<Private>)>) -> (X509, PKey<Private>) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_text("CN", name).unwrap();
+        let subject = subject.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(serial(name)).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&subject).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        match issuer {
+            Some((ca, ca_key)) => {
+                builder.set_issuer_name(ca.subject_name()).unwrap();
+                let san = SubjectAlternativeName::new().ip("127.0.0.1").dns("localhost")
+                    .build(&builder.x509v3_context(Some(ca), None)).unwrap();
+                builder.append_extension(san).unwrap();
+                builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                builder.set_issuer_name(&subject).unwrap();
+                builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+                builder.sign(&key, MessageDigest::sha256()).unwrap();
+            }
+        }
+        (builder.build(), key)
+    }
+
+    fn serial(name: &str) -> u32 {
+        name.bytes().map(u32::from).sum()
+    }
+
+    /// Answers every connection presenting a client certificate signed by `ca` with a 200.
+    fn serve(ca: &X509, cert: &X509, key: &PKey<Private>) -> String {
+        let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls()).unwrap();
+        acceptor.set_certificate(cert).unwrap();
+        acceptor.set_private_key(key).unwrap();
+        acceptor.cert_store_mut().add_cert(ca.clone()).unwrap();
+        acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        let acceptor = acceptor.build();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("https://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if let Ok(mut stream) = acceptor.accept(stream.unwrap()) {
+                    let mut buffer = [0; 4096];
+                    let _ = stream.read(&mut buffer);
+                    let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                }
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn client_certificates_and_custom_roots_are_used() {
+        let (ca, ca_key) = certificate("vaccine test ca", None);
+        let (server_cert, server_key) = certificate("localhost", Some((&ca, &ca_key)));
+        let (client_cert, client_key) = certificate("vaccine client", Some((&ca, &ca_key)));
+        let url = serve(&ca, &server_cert, &server_key);
+
+        let dir = std::env::temp_dir().join(format!("vaccine-tls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, contents: Vec<u8>| {
+            let path = dir.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        };
+        let ca_bundle = write("ca.pem", [b"# test roots\n".to_vec(), ca.to_pem().unwrap()].concat());
+        let cert = write("client.pem", client_cert.to_pem().unwrap());
+        let key = write("client.key", client_key.private_key_to_pem_pkcs8().unwrap());
+        let pkcs12 = write("client.p12", pem_to_pkcs12(&fs::read(&cert).unwrap(), &fs::read(&key).unwrap()).unwrap());
+
+        let send = |tls: TlsSettings| {
+            let settings = HttpSettings { tls, keep_alive: false, ..HttpSettings::default() };
+            ReqwestTransport::new(&settings).and_then(|transport| transport.send(RestRequest::new(Method::Get, &url)))
+        };
+        let pem = ClientIdentity::Pem { cert, key };
+
+        let response = send(TlsSettings { ca_bundle: Some(ca_bundle.clone()), identity: Some(pem.clone()), ..TlsSettings::default() });
+        assert_eq!(response.unwrap().status, 200);
+        let identity = ClientIdentity::Pkcs12 { path: pkcs12.clone(), password: String::new() };
+        let response = send(TlsSettings { ca_bundle: Some(ca_bundle.clone()), identity: Some(identity), ..TlsSettings::default() });
+        assert_eq!(response.unwrap().status, 200);
+        let response = send(TlsSettings { identity: Some(pem.clone()), insecure_skip_verify: true, ..TlsSettings::default() });
+        assert_eq!(response.unwrap().status, 200);
+
+        match send(TlsSettings { identity: Some(pem), ..TlsSettings::default() }) {
+            Err(RestClientError::Tls(_)) => {}
+            other => panic!("expected an untrusted server certificate, got {:?}", other),
+        }
+        assert!(send(TlsSettings { ca_bundle: Some(ca_bundle), ..TlsSettings::default() }).is_err());
+
+        let misconfigured = |tls: TlsSettings| match ReqwestTransport::new(&HttpSettings { tls, ..HttpSettings::default() }) {
+            Err(RestClientError::Tls(message)) => message,
+            other => panic!("expected a TLS configuration error, got {:?}", other),
+        };
+        let message = misconfigured(TlsSettings { ca_bundle: Some(dir.join("missing.pem")), ..TlsSettings::default() });
+        assert!(message.starts_with("failed to read ") && message.contains("missing.pem"), "{}", message);
+        let message = misconfigured(TlsSettings { ca_bundle: Some(dir.join("client.key")), ..TlsSettings::default() });
+        assert!(message.starts_with("no certificates found in "), "{}", message);
+        let identity = ClientIdentity::Pkcs12 { path: pkcs12, password: "wrong".to_owned() };
+        let message = misconfigured(TlsSettings { identity: Some(identity), ..TlsSettings::default() });
+        assert!(message.starts_with("invalid PKCS#12 archive "), "{}", message);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bundles_are_split_into_certificates() {
+        let bundle = "# root one\n-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+                      # root two\n-----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\ntruncated";
+        assert_eq!(split_pem_certificates(bundle.as_bytes()), vec![
+            "-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n".to_owned(),
+            "-----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n".to_owned(),
+        ]);
+        assert!(split_pem_certificates(b"not a bundle").is_empty());
+    }
+}
+
+#[cfg(test)]
+mod in_memory_transport {
+    use super::*;
+
+    #[test]
+    fn requests_are_routed_to_closures() {
+        let transport = InMemoryTransport::new()
+            .route(Method::Post, "/castles", |r| RestResponse::from_json(201, &r.json_body().unwrap()))
+            .route(Method::Get, "/castles/*", |_| RestResponse::from_json(200, &json!({"id": 1})));
+
+        let created = transport.send(RestRequest::new(Method::Post, "http://x/castles").json(&json!({"a": 1})))
+            .unwrap();
+        assert_eq!(created.status, 201);
+        assert_eq!(created.json, Some(json!({"a": 1})));
+        assert_eq!(created.url, "http://x/castles");
+
+        let fetched = transport.send(RestRequest::new(Method::Get, "http://x/castles/1/")).unwrap();
+        assert_eq!(fetched.json, Some(json!({"id": 1})));
+
+        let missing = transport.send(RestRequest::new(Method::Delete, "http://x/castles/1")).unwrap();
+        assert_eq!(missing.status, 404);
+
+        let methods: Vec<Method> = transport.requests().iter().map(|r| r.method).collect();
+        assert_eq!(methods, vec![Method::Post, Method::Get, Method::Delete]);
+    }
+
+    #[test]
+    fn request_paths() {
+        assert_eq!(RestRequest::new(Method::Get, "http://localhost:8000/a/b?c=d").path(), "/a/b?c=d");
+        assert_eq!(RestRequest::new(Method::Get, "http://localhost:8000").path(), "/");
+        assert!(path_matches("/a/*", "/a/12?x=1"));
+        assert!(!path_matches("/a/*", "/a/12/b"));
+    }
+}
@@ -1,64 +1,116 @@
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
 
 use serde_json::Value;
 
+use crate::definitions::resolve_refs;
+use crate::expectations::Expectations;
+use crate::interpolation::interpolate_schema;
+use crate::profiles::apply_profile;
+use crate::request_parameters::RequestParameters;
 use crate::rest_endpoint::Endpoint;
+use crate::schema_file::load_schema;
 use rest_client::AuthType;
+use rest_client::login::Login;
 
 #[derive(Debug)]
 pub struct Session {
     pub root_url: String,
     pub auth_type: Option<AuthType>,
+    /// How to log in for a bearer or JWT token, instead of reading one from the environment.
+    pub login: Option<Login>,
+    pub expectations: Expectations,
+    /// Headers, query parameters and cookies sent with the requests to every endpoint.
+    pub parameters: RequestParameters,
     pub endpoints: HashMap<String, Endpoint>,
     pub created: HashMap<String, Value>,
 }
 
 impl Session {
     pub fn from_file(path: &str) -> Self {
-        let mut file = File::open(path).expect(&format!("failed to open {}", path));
+        Self::from_file_with_profile(path, None)
+    }
 
-        let mut buffer = String::new();
-        file.read_to_string(&mut buffer).expect("failed to read file");
+    /// Reads the JSON, YAML or TOML schema at `path`, and the files it includes, with the named entry
+    /// of its `profiles` applied, if any.
+    pub fn from_file_with_profile(path: &str, profile: Option<&str>) -> Self {
+        let value = load_schema(path).unwrap_or_else(|e| panic!("{}", e));
 
-        let value = serde_json::from_str(buffer.as_str())
-            .expect(&format!("failed to read json from {}", path));
+        match profile {
+            Some(profile) => Self::with_profile(&value, profile),
+            None => Self::new(&value),
+        }
+    }
 
-        Self::new(&value)
+    /// A session for one environment, with the fields and defaults of the named profile in place.
+    pub fn with_profile(v: &Value, profile: &str) -> Self {
+        Self::new(&apply_profile(&resolved(v), profile).unwrap_or_else(|e| panic!("{}", e)))
     }
 
+    /// Builds a session from a schema, after replacing `$ref`s by their definitions and expanding
+    /// `${ENV}`, `${ENV:-default}` and `${file:path}` in its strings.
     pub fn new(v: &Value) -> Self {
+        let raw = resolved(v);
+        let v = &interpolate_schema(&raw).unwrap_or_else(|e| panic!("{}", e));
 
         // Make sure the types match
         assert!(v.is_object());
         assert!(v["endpoints"].is_array());
 
-        // Build up the endpoints
+        let expectations = v.get("expectations")
+            .map(Expectations::new)
+            .unwrap_or_default();
+
+        // Build up the endpoints, falling back to the session wide expectations
         let endpoints: HashMap<String, Endpoint> = v["endpoints"].as_array()
             .unwrap()
             .iter()
-            .map(|v| Endpoint::new(v))
+            .zip(raw["endpoints"].as_array().unwrap())
+            .map(|(v, raw)| {
+                let mut e = Endpoint::new(v);
+                e.expectations = e.expectations.merged_with(&expectations);
+                e.parameters.remember_templates(raw);
+                e
+            })
             .map(|e| (e.name.clone(), e))
             .collect();
 
-        let auth_type: Option<AuthType> = if let Some(v) = v.get("auth_type") {
-            serde_json::from_value(v.clone()).ok()
-        } else {
-            None
-        };
+        // A misspelt auth type would otherwise run the whole campaign unauthenticated
+        let auth_type: Option<AuthType> = v.get("auth_type").map(|auth_type| {
+            serde_json::from_value(auth_type.clone())
+                .unwrap_or_else(|e| panic!("`auth_type` is not a known auth type: {}", e))
+        });
+
+        let login = v.get("login")
+            .map(|l| serde_json::from_value(l.clone()).expect("`login` needs a url, body and token_pointer"));
+        match (&login, &auth_type) {
+            (Some(_), Some(AuthType::Bearer)) | (Some(_), Some(AuthType::JWT)) | (None, _) => {}
+            (Some(_), other) => panic!("`login` is only used with Bearer or JWT auth, not {:?}", other),
+        }
+
+        let mut parameters = RequestParameters::new(v);
+        parameters.remember_templates(&raw);
 
         Session {
             endpoints,
+            login,
             created: HashMap::new(),
             root_url: v["root_url"].as_str().expect("root_url missing or not string").to_string(),
-            auth_type
+            auth_type,
+            expectations,
+            parameters,
         }
     }
 }
 
+/// `v` with its `$ref`s replaced by their definitions.
+fn resolved(v: &Value) -> Value {
+    resolve_refs(v).unwrap_or_else(|e| panic!("{}", e))
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::expectations::StatusPattern;
+
     use super::*;
 
     #[test]
@@ -158,4 +210,145 @@ mod tests {
         let config = Session::new(&v);
         assert_eq!(config.endpoints.len(), 2);
     }
+
+    #[test]
+    fn test_endpoint_expectations_fall_back_to_session() {
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "expectations": { "valid": [200], "never": ["5xx"] },
+            "endpoints": [
+                { "name": "a", "url": "/a", "requires": [], "schema": [],
+                  "expectations": { "valid": [204] } },
+                { "name": "b", "url": "/b", "requires": [], "schema": [] }
+            ]
+        });
+
+        let config = Session::new(&v);
+        let a = &config.endpoints["a"].expectations;
+        assert_eq!(a.valid, Some(vec![StatusPattern::Exact(204)]));
+        assert_eq!(a.never, Some(vec![StatusPattern::Class(5)]));
+
+        let b = &config.endpoints["b"].expectations;
+        assert_eq!(b.valid, Some(vec![StatusPattern::Exact(200)]));
+    }
+
+    #[test]
+    fn test_login_block() {
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "auth_type": "JWT",
+            "login": {
+                "url": "/api/token/",
+                "body": { "username": "{username}", "password": "{password}" },
+                "token_pointer": "/access"
+            },
+            "endpoints": []
+        });
+
+        let config = Session::new(&v);
+        assert_eq!(config.auth_type, Some(AuthType::JWT));
+        let login = config.login.unwrap();
+        assert_eq!(login.url, "/api/token/");
+        assert_eq!(login.token_pointer, "/access");
+        assert!(Session::new(&json!({ "root_url": "/", "endpoints": [] })).login.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "`login` is only used with Bearer or JWT auth, not Some(Basic)")]
+    fn test_login_needs_token_auth() {
+        Session::new(&json!({
+            "root_url": "http://localhost:8000",
+            "auth_type": "Basic",
+            "login": { "url": "/api/token/", "body": {}, "token_pointer": "/access" },
+            "endpoints": []
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "`auth_type` is not a known auth type: unknown variant `ApiKye`")]
+    fn test_unknown_auth_type() {
+        Session::new(&json!({
+            "root_url": "http://localhost:8000",
+            "auth_type": { "ApiKye": { "name": "X-API-Key", "location": "header" } },
+            "endpoints": []
+        }));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "endpoints": [
+                { "name": "a", "url": "/a", "requires": [], "schema": [
+                    { "name": "houseType", "kind": "string", "default_value": "castle" }
+                ] }
+            ],
+            "profiles": {
+                "staging": {
+                    "root_url": "https://staging.example.com",
+                    "auth_type": "Bearer",
+                    "defaults": { "a": { "houseType": "fort" } }
+                }
+            }
+        });
+
+        let local = Session::new(&v);
+        assert_eq!(local.root_url, "http://localhost:8000");
+        assert_eq!(local.auth_type, None);
+
+        let staging = Session::with_profile(&v, "staging");
+        assert_eq!(staging.root_url, "https://staging.example.com");
+        assert_eq!(staging.auth_type, Some(AuthType::Bearer));
+        assert_eq!(staging.endpoints["a"].default_payload(), json!({ "houseType": "fort" }));
+    }
+
+    #[test]
+    fn test_definitions() {
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "definitions": {
+                "address": { "name": "address", "kind": "mapping", "schema": [
+                    { "name": "city", "kind": "string", "default_value": "Pune" }
+                ] }
+            },
+            "endpoints": [
+                { "name": "a", "url": "/a", "requires": [], "schema": [{ "$ref": "#/definitions/address" }] }
+            ],
+            "profiles": { "remote": { "defaults": { "a": { "address.city": "Goa" } } } }
+        });
+
+        assert_eq!(Session::new(&v).endpoints["a"].default_payload(), json!({ "address": { "city": "Pune" } }));
+        let remote = Session::with_profile(&v, "remote");
+        assert_eq!(remote.endpoints["a"].default_payload(), json!({ "address": { "city": "Goa" } }));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        std::env::set_var("VACCINE_TEST_HOST", "staging.example.com");
+        let v = json!({
+            "root_url": "https://${VACCINE_TEST_HOST}",
+            "headers": { "X-Tenant-Id": "${VACCINE_TEST_TENANT_ID:-42}" },
+            "endpoints": [
+                { "name": "a", "url": "/a", "requires": [], "schema": [
+                    { "name": "owner", "kind": "string", "default_value": "${VACCINE_TEST_OWNER:-jumbo}" }
+                ] }
+            ],
+            "profiles": { "broken": { "root_url": "${VACCINE_TEST_UNSET_URL}" } }
+        });
+
+        let session = Session::new(&v);
+        assert_eq!(session.root_url, "https://staging.example.com");
+        assert_eq!(session.parameters.default_options().headers, vec![("x-tenant-id".to_owned(), "42".to_owned())]);
+        assert_eq!(session.endpoints["a"].default_payload(), json!({ "owner": "jumbo" }));
+    }
+
+    #[test]
+    #[should_panic(expected = "${VACCINE_TEST_UNSET_URL} at /root_url: not set")]
+    fn test_unresolved_variables() {
+        Session::with_profile(&json!({
+            "root_url": "http://localhost",
+            "endpoints": [],
+            "profiles": { "broken": { "root_url": "${VACCINE_TEST_UNSET_URL}" } }
+        }), "broken");
+    }
 }
@@ -1,4 +1,4 @@
-use chrono::Utc;
+use chrono::{NaiveDate, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::Tz;
 use humantime::parse_duration;
 
@@ -25,10 +25,39 @@ pub fn datetime_from_now(date_string: &str, tzname: &str) -> chrono::DateTime<Tz
     }
 }
 
+/// Parses a datetime written in `format`, falling back to RFC 3339. Values without an
+/// offset are taken to be in `tzname`.
+pub fn parse_datetime(s: &str, format: &str, tzname: &str) -> Option<chrono::DateTime<Utc>> {
+    if let Ok(d) = chrono::DateTime::parse_from_str(s, format) {
+        return Some(d.with_timezone(&Utc));
+    }
+
+    let tz: Tz = tzname.parse().ok()?;
+    let naive = NaiveDateTime::parse_from_str(s, format).ok()
+        .or_else(|| NaiveDate::parse_from_str(s, format).ok().and_then(|d| d.and_hms_opt(0, 0, 0)));
+    if let Some(naive) = naive {
+        return tz.from_local_datetime(&naive).single().map(|d| d.with_timezone(&Utc));
+    }
+
+    chrono::DateTime::parse_from_rfc3339(s).ok().map(|d| d.with_timezone(&Utc))
+}
+
 pub fn is_now(s: &str) -> bool {
     s.starts_with("now")
 }
 
+/// Why `s`, a datetime relative to now, cannot be read, if it cannot.
+pub fn invalid_now(s: &str) -> Option<String> {
+    match get_direction(s) {
+        None => Some(format!("{} is not `now`, `now + <duration>` or `now - <duration>`", s)),
+        Some(Here) => None,
+        Some(_) => {
+            let duration: String = s.chars().skip("now . ".len()).collect();
+            parse_duration(&duration).err().map(|e| format!("invalid duration {} in {}: {}", duration, s, e))
+        }
+    }
+}
+
 fn get_direction(s: &str) -> Option<Direction> {
     if s == "now" {
         Some(Here)
@@ -132,6 +161,18 @@ mod tests {
         assert_eq!(now.date(), d.date());
     }
 
+    #[test]
+    fn parse_datetime_normalizes_timezones() {
+        let sent = parse_datetime("2001-01-01 11:22:33", "%Y-%m-%d %H:%M:%S", "Asia/Kolkata").unwrap();
+        let stored = parse_datetime("2001-01-01T05:52:33Z", "%Y-%m-%d %H:%M:%S", "Asia/Kolkata").unwrap();
+        assert_eq!(sent, stored);
+
+        let date = parse_datetime("2001-01-01", "%Y-%m-%d", "Asia/Kolkata").unwrap();
+        assert_eq!(date, chrono::DateTime::parse_from_rfc3339("2000-12-31T18:30:00Z").unwrap());
+
+        assert!(parse_datetime("yesterday", "%Y-%m-%d", "Asia/Kolkata").is_none());
+    }
+
     #[test]
     fn parse_now() {
         let now = Utc::now().with_timezone(&Asia::Kolkata);
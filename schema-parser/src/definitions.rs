@@ -0,0 +1,135 @@
+use serde_json::{Map, Value};
+
+/// Replaces every `{"$ref": "#/definitions/<name>"}` found in an array of `v` with the definition.
+///
+/// A definition holding an array of fields is spliced into the array in place of the reference. One
+/// holding a single entry replaces it, with any other keys next to `$ref`, such as `name`, overriding
+/// those of the definition. Definitions may refer to other definitions, but not to themselves.
+pub fn resolve_refs(v: &Value) -> Result<Value, String> {
+    let fields = match v.as_object() {
+        Some(fields) => fields,
+        None => return Ok(v.clone()),
+    };
+
+    let mut resolved = Map::new();
+    for (name, value) in fields {
+        // Definitions are resolved where they are used
+        let value = if name == "definitions" { value.clone() } else { resolve(v, value, &mut Vec::new())? };
+        resolved.insert(name.clone(), value);
+    }
+    Ok(Value::Object(resolved))
+}
+
+fn resolve(root: &Value, v: &Value, resolving: &mut Vec<String>) -> Result<Value, String> {
+    match v {
+        Value::Array(items) => {
+            let mut resolved = Vec::with_capacity(items.len());
+            for item in items {
+                match item.get("$ref") {
+                    Some(reference) => match expand(root, item, reference, resolving)? {
+                        Value::Array(fields) => resolved.extend(fields),
+                        entry => resolved.push(entry),
+                    },
+                    None => resolved.push(resolve(root, item, resolving)?),
+                }
+            }
+            Ok(Value::Array(resolved))
+        }
+        Value::Object(fields) => {
+            let mut resolved = Map::new();
+            for (name, value) in fields {
+                resolved.insert(name.clone(), resolve(root, value, resolving)?);
+            }
+            Ok(Value::Object(resolved))
+        }
+        other => Ok(other.clone()),
+    }
+}
+
+fn expand(root: &Value, item: &Value, reference: &Value, resolving: &mut Vec<String>) -> Result<Value, String> {
+    let reference = reference.as_str().ok_or("`$ref` is not a string")?;
+    if !reference.starts_with("#/") {
+        return Err(format!("unsupported $ref {}, only references into this schema like #/definitions/address are", reference));
+    }
+    if resolving.iter().any(|r| r == reference) {
+        return Err(format!("$ref cycle: {} -> {}", resolving.join(" -> "), reference));
+    }
+    let definition = root.pointer(&reference[1..])
+        .ok_or_else(|| format!("$ref {} does not point at anything", reference))?;
+
+    resolving.push(reference.to_owned());
+    let definition = resolve(root, definition, resolving);
+    resolving.pop();
+
+    match definition? {
+        Value::Object(mut entry) => {
+            for (name, value) in item.as_object().unwrap() {
+                if name != "$ref" {
+                    entry.insert(name.clone(), value.clone());
+                }
+            }
+            Ok(Value::Object(entry))
+        }
+        Value::Array(fields) => {
+            if item.as_object().unwrap().len() > 1 {
+                return Err(format!("$ref {} is a list of fields, which cannot be given other keys", reference));
+            }
+            Ok(Value::Array(fields))
+        }
+        _ => Err(format!("$ref {} is neither a schema entry nor a list of them", reference)),
+    }
+}
+
+#[cfg(test)]
+mod definitions {
+    use super::*;
+
+    #[test]
+    fn references_are_replaced_by_definitions() {
+        let v = json!({
+            "definitions": {
+                "address": { "name": "address", "kind": "mapping", "schema": [
+                    { "name": "city", "kind": "string", "default_value": "Pune" },
+                    { "$ref": "#/definitions/audit" }
+                ] },
+                "audit": [
+                    { "name": "createdBy", "kind": "string", "default_value": "vaccine" },
+                    { "name": "version", "kind": "number", "default_value": 1 }
+                ]
+            },
+            "endpoints": [
+                { "name": "house", "schema": [
+                    { "name": "houseType", "kind": "string", "default_value": "castle" },
+                    { "$ref": "#/definitions/address", "name": "billingAddress" },
+                    { "$ref": "#/definitions/audit" }
+                ] }
+            ]
+        });
+
+        let resolved = resolve_refs(&v).unwrap();
+        let schema = &resolved["endpoints"][0]["schema"];
+        let names: Vec<&str> = schema.as_array().unwrap().iter().map(|f| f["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["houseType", "billingAddress", "createdBy", "version"]);
+        assert_eq!(schema[1]["schema"][2]["name"], "version");
+        assert_eq!(resolved["definitions"], v["definitions"]);
+    }
+
+    #[test]
+    fn broken_references_are_reported() {
+        let cycle = json!({
+            "definitions": {
+                "a": { "name": "a", "kind": "mapping", "schema": [{ "$ref": "#/definitions/b" }] },
+                "b": { "name": "b", "kind": "mapping", "schema": [{ "$ref": "#/definitions/a" }] }
+            },
+            "endpoints": [{ "name": "x", "schema": [{ "$ref": "#/definitions/a" }] }]
+        });
+        assert_eq!(resolve_refs(&cycle).unwrap_err(),
+                   "$ref cycle: #/definitions/a -> #/definitions/b -> #/definitions/a");
+
+        let missing = json!({ "endpoints": [{ "name": "x", "schema": [{ "$ref": "#/definitions/money" }] }] });
+        assert_eq!(resolve_refs(&missing).unwrap_err(), "$ref #/definitions/money does not point at anything");
+
+        let remote = json!({ "endpoints": [{ "name": "x", "schema": [{ "$ref": "money.json" }] }] });
+        assert!(resolve_refs(&remote).unwrap_err().starts_with("unsupported $ref money.json"));
+    }
+}
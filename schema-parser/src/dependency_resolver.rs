@@ -1,27 +1,238 @@
-//use std::collections::HashMap;
-//
-//use serde_json::Value;
-//
-//use rest_client::RestClient;
-//
-//use crate::configuration::Configuration;
-//use crate::rest_endpoint::Endpoint;
-//
-//pub fn create_item(
-//    endpoint: &Endpoint,
-//    created_items: &mut HashMap<String, Value>,
-//    configuration: &Configuration,
-//    client: &RestClient,
-//) {
-//    for dependency in &endpoint.requires {
-//        if !created_items.contains_key(dependency) {
-//            create_item(&configuration.endpoints[dependency], created_items, configuration, client);
-//        }
-//    }
-//
-//    let response = client.post(&endpoint.url, &endpoint.default_payload());
-//    match response {
-//        None => panic!(format!("Failed to create endpoint {}. Stopping here!", endpoint.name)),
-//        Some(_) => created_items[endpoint.name.clone()] = response.unwrap(),
-//    }
-//}
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use rest_client::{Method, RestClient};
+
+use crate::configuration::Session;
+use crate::rest_endpoint::Endpoint;
+
+/// The request which created an object of the graph, and the response to it.
+#[derive(Debug, Clone)]
+pub struct Creation {
+    pub endpoint: String,
+    pub url: String,
+    pub payload: Value,
+    pub status: u16,
+    pub body: Value,
+}
+
+/// An object of the graph which could not be deleted during teardown.
+#[derive(Debug, Clone)]
+pub struct FailedDeletion {
+    pub endpoint: String,
+    pub url: String,
+    pub reason: String,
+}
+
+/// Creates an object for every endpoint, dependencies first, and returns the creations in
+/// the order they were made. Stops at the first object which can not be created, or at `requires`
+/// which lead back to the endpoint requiring them.
+pub fn build_object_graph(session: &mut Session, client: &RestClient) -> Result<Vec<Creation>, String> {
+    let mut names: Vec<String> = session.endpoints.keys().cloned().collect();
+    names.sort();
+
+    let mut creations = vec![];
+    for name in names {
+        if !session.created.contains_key(&name) {
+            let endpoint = &session.endpoints[&name];
+            create_item(endpoint, &session.endpoints, &mut session.created, client, &mut vec![], &mut creations)?;
+        }
+    }
+    Ok(creations)
+}
+
+/// Creates `endpoint` after its dependencies, `path` being the endpoints waiting on it.
+fn create_item(
+    endpoint: &Endpoint,
+    endpoints: &HashMap<String, Endpoint>,
+    created_items: &mut HashMap<String, Value>,
+    client: &RestClient,
+    path: &mut Vec<String>,
+    creations: &mut Vec<Creation>,
+) -> Result<(), String> {
+    path.push(endpoint.name.clone());
+    for dependency in &endpoint.requires {
+        if let Some(start) = path.iter().position(|name| name == dependency) {
+            return Err(format!("requires cycle {} -> {}", path[start..].join(" -> "), dependency));
+        }
+        if !created_items.contains_key(dependency) {
+            let required = endpoints.get(dependency)
+                .ok_or_else(|| format!("{} requires unknown endpoint {}", endpoint.name, dependency))?;
+            create_item(required, endpoints, created_items, client, path, creations)?;
+        }
+    }
+    path.pop();
+
+    let payload = endpoint.default_payload();
+    let options = endpoint.parameters.default_options();
+    let response = client.request_with(Method::Post, &endpoint.url, Some(&payload), &options)
+        .map_err(|e| format!("Failed to create endpoint {}: {}. Stopping here!", endpoint.name, e))?;
+
+    if !response.is_success() {
+        return Err(format!("Failed to create endpoint {}, got status {}. Stopping here!", endpoint.name, response.status));
+    }
+
+    let created: Value = response.json
+        .ok_or_else(|| format!("response for {} is not JSON!", endpoint.name))?;
+    created_items.insert(endpoint.name.clone(), created.clone());
+    creations.push(Creation {
+        endpoint: endpoint.name.clone(),
+        url: endpoint.url.clone(),
+        payload,
+        status: response.status,
+        body: created,
+    });
+    Ok(())
+}
+
+/// The created objects ordered so that every object comes after the objects requiring it.
+pub fn deletion_order(session: &Session) -> Vec<String> {
+    let mut names: Vec<&String> = session.created.keys().collect();
+    names.sort();
+
+    let mut order = vec![];
+    for name in names {
+        visit_dependencies(name, session, &mut order);
+    }
+    order.reverse();
+    order
+}
+
+fn visit_dependencies(name: &str, session: &Session, order: &mut Vec<String>) {
+    if order.iter().any(|n| n == name) || !session.created.contains_key(name) {
+        return;
+    }
+    if let Some(endpoint) = session.endpoints.get(name) {
+        for dependency in &endpoint.requires {
+            visit_dependencies(dependency, session, order);
+        }
+    }
+    order.push(name.to_owned());
+}
+
+/// Deletes the created objects in reverse dependency order, returning the ones which could not be.
+pub fn teardown_object_graph(session: &mut Session, client: &RestClient) -> Vec<FailedDeletion> {
+    let mut failures = vec![];
+
+    for name in deletion_order(session) {
+        let endpoint = &session.endpoints[&name];
+        let url = endpoint.delete_url(&session.created[&name]);
+        let reason = match client.request_with(Method::Delete, &url, None, &endpoint.parameters.default_options()) {
+            Ok(ref response) if response.is_success() => None,
+            Ok(response) => Some(format!("got status {}", response.status)),
+            Err(e) => Some(e.to_string()),
+        };
+
+        match reason {
+            None => {
+                session.created.remove(&name);
+            }
+            Some(reason) => failures.push(FailedDeletion { endpoint: name, url, reason }),
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use rest_client::config_builder::ClientConfigurationBuilder;
+    use rest_client::response::RestResponse;
+    use rest_client::transport::{InMemoryTransport, RestRequest};
+
+    use super::*;
+
+    fn castle_session() -> Session {
+        Session::new(&json!({
+            "root_url": "http://castles",
+            "endpoints": [
+                { "name": "moat", "url": "/moats", "requires": ["castle"],
+                  "headers": { "X-Moat-Key": "drawbridge" },
+                  "schema": [{ "name": "depth", "kind": "number", "default_value": 3 }] },
+                { "name": "castle", "url": "/castles", "requires": [],
+                  "schema": [{ "name": "name", "kind": "string", "default_value": "Jumbo" }] }
+            ]
+        }))
+    }
+
+    #[test]
+    fn graph_is_built_and_torn_down_in_dependency_order() {
+        let created = |id: i64| move |r: &RestRequest| {
+            let mut body = r.json_body().unwrap();
+            body["id"] = json!(id);
+            RestResponse::from_json(201, &body)
+        };
+        let transport = InMemoryTransport::new()
+            .route(Method::Post, "/castles", created(1))
+            .route(Method::Post, "/moats", created(2))
+            .route(Method::Delete, "/moats/2", |r| {
+                assert_eq!(r.header_value("x-moat-key"), Some("drawbridge"));
+                RestResponse::from_json(204, &json!(null))
+            })
+            .route(Method::Delete, "/castles/1", |_| RestResponse::from_json(500, &json!(null)));
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        let mut session = castle_session();
+        let creations = build_object_graph(&mut session, &client).unwrap();
+        let order: Vec<&str> = creations.iter().map(|c| c.endpoint.as_str()).collect();
+        assert_eq!(order, vec!["castle", "moat"]);
+        assert_eq!(session.created["moat"], json!({ "id": 2, "depth": 3 }));
+
+        let failures = teardown_object_graph(&mut session, &client);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].endpoint, "castle");
+        assert_eq!(failures[0].reason, "got status 500");
+        assert!(!session.created.contains_key("moat"));
+    }
+
+    #[test]
+    fn requires_cycles_are_refused() {
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(InMemoryTransport::new().route(Method::Post, "/kingdoms", |r| {
+                RestResponse::from_json(201, &r.json_body().unwrap())
+            }))
+            .build()
+            .unwrap();
+        let mut session = Session::new(&json!({
+            "root_url": "http://castles",
+            "endpoints": [
+                { "name": "armoury", "url": "/armouries", "requires": ["kingdom", "moat"], "schema": [] },
+                { "name": "kingdom", "url": "/kingdoms", "requires": [], "schema": [] },
+                { "name": "moat", "url": "/moats", "requires": ["bridge"], "schema": [] },
+                { "name": "bridge", "url": "/bridges", "requires": ["moat"], "schema": [] }
+            ]
+        }));
+
+        assert_eq!(build_object_graph(&mut session, &client).unwrap_err(), "requires cycle moat -> bridge -> moat");
+        assert!(session.created.contains_key("kingdom"));
+    }
+
+    #[test]
+    fn dependents_are_deleted_before_their_dependencies() {
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "endpoints": [
+                { "name": "moat", "url": "/moat", "requires": ["castle"], "schema": [] },
+                { "name": "castle", "url": "/castle", "requires": ["kingdom"], "schema": [] },
+                { "name": "kingdom", "url": "/kingdom", "requires": [], "schema": [] },
+                { "name": "dragon", "url": "/dragon", "requires": ["kingdom"], "schema": [] }
+            ]
+        });
+        let mut session = Session::new(&v);
+        for name in &["moat", "castle", "kingdom", "dragon"] {
+            session.created.insert(name.to_string(), json!({ "id": 1 }));
+        }
+
+        let order = deletion_order(&session);
+        let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+        assert_eq!(order.len(), 4);
+        assert!(position("moat") < position("castle"));
+        assert!(position("castle") < position("kingdom"));
+        assert!(position("dragon") < position("kingdom"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt::{Display, Error, Formatter};
+
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MutationClass {
+    Valid,
+    TypeConfused,
+}
+
+impl MutationClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MutationClass::Valid => "valid",
+            MutationClass::TypeConfused => "type_confused",
+        }
+    }
+}
+
+/// Either an exact status code like `204` or a whole class of them like `"4xx"`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusPattern {
+    Exact(u16),
+    Class(u16),
+}
+
+impl StatusPattern {
+    pub fn new(v: &Value) -> Self {
+        StatusPattern::parse(v).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn parse(v: &Value) -> Result<Self, String> {
+        if let Some(status) = v.as_u64() {
+            if !(100..=599).contains(&status) {
+                return Err(format!("invalid status {}, expected 100 to 599", status));
+            }
+            return Ok(StatusPattern::Exact(status as u16));
+        }
+
+        let s = v.as_str().ok_or_else(|| format!("invalid status {}", v))?;
+        Some(s)
+            .filter(|s| s.len() == 3 && s.ends_with("xx"))
+            .and_then(|s| s[..1].parse::<u16>().ok())
+            .filter(|c| (1..=5).contains(c))
+            .map(StatusPattern::Class)
+            .ok_or_else(|| format!("invalid status class {}, expected e.g. 4xx", s))
+    }
+
+    pub fn matches(&self, status: u16) -> bool {
+        match self {
+            StatusPattern::Exact(s) => *s == status,
+            StatusPattern::Class(c) => status / 100 == *c,
+        }
+    }
+}
+
+impl Display for StatusPattern {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            StatusPattern::Exact(s) => write!(f, "{}", s),
+            StatusPattern::Class(c) => write!(f, "{}xx", c),
+        }
+    }
+}
+
+/// The statuses each class of payload is allowed to produce, plus the ones no payload may.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expectations {
+    pub valid: Option<Vec<StatusPattern>>,
+    pub type_confused: Option<Vec<StatusPattern>>,
+    pub never: Option<Vec<StatusPattern>>,
+}
+
+impl Expectations {
+    pub fn new(v: &Value) -> Self {
+        assert!(v.is_object(), "expectations is not an object");
+        Expectations {
+            valid: parse_patterns(v, "valid"),
+            type_confused: parse_patterns(v, "type_confused"),
+            never: parse_patterns(v, "never"),
+        }
+    }
+
+    /// Fills in the rules missing here from `fallback`.
+    pub fn merged_with(&self, fallback: &Expectations) -> Expectations {
+        Expectations {
+            valid: self.valid.clone().or_else(|| fallback.valid.clone()),
+            type_confused: self.type_confused.clone().or_else(|| fallback.type_confused.clone()),
+            never: self.never.clone().or_else(|| fallback.never.clone()),
+        }
+    }
+
+    pub fn check(&self, class: MutationClass, status: u16) -> Result<(), String> {
+        if let Some(never) = &self.never {
+            if never.iter().any(|p| p.matches(status)) {
+                return Err(format!("{} is never expected", status));
+            }
+        }
+
+        let allowed = match class {
+            MutationClass::Valid => &self.valid,
+            MutationClass::TypeConfused => &self.type_confused,
+        };
+        match allowed {
+            Some(allowed) if !allowed.iter().any(|p| p.matches(status)) => {
+                let allowed: Vec<String> = allowed.iter().map(|p| p.to_string()).collect();
+                Err(format!("{} for a {} payload, expected one of {}", status, class.as_str(), allowed.join(", ")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn parse_patterns(v: &Value, key: &str) -> Option<Vec<StatusPattern>> {
+    v.get(key).map(|statuses| {
+        statuses.as_array()
+            .expect(&format!("expectation {} is not an array", key))
+            .iter()
+            .map(StatusPattern::new)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_expectations() -> Expectations {
+        Expectations::new(&json!({
+            "valid": [200, 204],
+            "type_confused": ["4xx"],
+            "never": [500]
+        }))
+    }
+
+    #[test]
+    fn status_patterns() {
+        assert_eq!(StatusPattern::new(&json!(204)), StatusPattern::Exact(204));
+        assert_eq!(StatusPattern::new(&json!("4xx")), StatusPattern::Class(4));
+        assert!(StatusPattern::Class(4).matches(422));
+        assert!(!StatusPattern::Class(4).matches(500));
+        assert_eq!(StatusPattern::Class(2).to_string(), "2xx");
+    }
+
+    #[test]
+    fn invalid_statuses_are_described() {
+        assert_eq!(StatusPattern::parse(&json!(700)).unwrap_err(), "invalid status 700, expected 100 to 599");
+        assert_eq!(StatusPattern::parse(&json!("6xx")).unwrap_err(), "invalid status class 6xx, expected e.g. 4xx");
+        assert_eq!(StatusPattern::parse(&json!(true)).unwrap_err(), "invalid status true");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid status 65736, expected 100 to 599")]
+    fn out_of_range_statuses_are_refused() {
+        StatusPattern::new(&json!(65736));
+    }
+
+    #[test]
+    fn statuses_are_checked_per_mutation_class() {
+        let e = create_expectations();
+        assert!(e.check(MutationClass::Valid, 204).is_ok());
+        assert!(e.check(MutationClass::TypeConfused, 422).is_ok());
+
+        let missing_validation = e.check(MutationClass::TypeConfused, 200).unwrap_err();
+        assert_eq!(missing_validation, "200 for a type_confused payload, expected one of 4xx");
+        assert!(e.check(MutationClass::Valid, 400).is_err());
+        assert_eq!(e.check(MutationClass::Valid, 500).unwrap_err(), "500 is never expected");
+    }
+
+    #[test]
+    fn missing_rules_fall_back() {
+        let endpoint = Expectations::new(&json!({ "valid": [201] }));
+        let merged = endpoint.merged_with(&create_expectations());
+        assert_eq!(merged.valid, Some(vec![StatusPattern::Exact(201)]));
+        assert_eq!(merged.type_confused, Some(vec![StatusPattern::Class(4)]));
+
+        assert!(Expectations::default().check(MutationClass::TypeConfused, 200).is_ok());
+    }
+}
@@ -0,0 +1,179 @@
+use std::env;
+use std::fmt::{Display, Error, Formatter};
+use std::fs;
+
+use serde_json::{Map, Value};
+
+/// A `${...}` which could not be expanded, where in the schema it was found and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unresolved {
+    pub reference: String,
+    pub location: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterpolationError {
+    pub unresolved: Vec<Unresolved>,
+}
+
+impl Display for InterpolationError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "unresolved variables in the schema:")?;
+        for u in &self.unresolved {
+            write!(f, "\n  ${{{}}} at {}: {}", u.reference, u.location, u.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InterpolationError {}
+
+/// Expands every string in the schema `v` except those under `profiles`, which are expanded once
+/// one is picked, and `definitions`, which are expanded where they are used.
+pub fn interpolate_schema(v: &Value) -> Result<Value, InterpolationError> {
+    let mut unresolved = Vec::new();
+    let interpolated = match v {
+        Value::Object(fields) => fields.iter()
+            .map(|(name, value)| {
+                let value = match name.as_str() {
+                    "profiles" | "definitions" => value.clone(),
+                    _ => interpolate_value(value, &format!("/{}", name), &mut unresolved),
+                };
+                (name.clone(), value)
+            })
+            .collect::<Map<String, Value>>()
+            .into(),
+        other => interpolate_value(other, "", &mut unresolved),
+    };
+
+    if unresolved.is_empty() {
+        Ok(interpolated)
+    } else {
+        Err(InterpolationError { unresolved })
+    }
+}
+
+fn interpolate_value(v: &Value, location: &str, unresolved: &mut Vec<Unresolved>) -> Value {
+    match v {
+        Value::String(s) => match interpolate(s) {
+            Ok(s) => Value::from(s),
+            Err(errors) => {
+                unresolved.extend(errors.into_iter().map(|(reference, reason)| {
+                    Unresolved { reference, location: location.to_owned(), reason }
+                }));
+                v.clone()
+            }
+        },
+        Value::Array(items) => items.iter()
+            .enumerate()
+            .map(|(i, item)| interpolate_value(item, &format!("{}/{}", location, i), unresolved))
+            .collect(),
+        Value::Object(fields) => fields.iter()
+            .map(|(name, value)| {
+                let location = format!("{}/{}", location, name.replace('~', "~0").replace('/', "~1"));
+                (name.clone(), interpolate_value(value, &location, unresolved))
+            })
+            .collect::<Map<String, Value>>()
+            .into(),
+        other => other.clone(),
+    }
+}
+
+/// Expands `${NAME}` and `${NAME:-default}` from the environment and `${file:path}` to the contents of
+/// the file, without a trailing newline. `$${` is a literal `${`.
+///
+/// Fails with each reference that could not be expanded and the reason.
+pub fn interpolate(s: &str) -> Result<String, Vec<(String, String)>> {
+    let mut out = String::new();
+    let mut errors = Vec::new();
+    let mut rest = s;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => {
+                errors.push((rest[start + 2..].to_owned(), "no closing }".to_owned()));
+                rest = "";
+                break;
+            }
+        };
+        let reference = &rest[start + 2..end];
+        match expand(reference) {
+            Ok(value) => out.push_str(&value),
+            Err(reason) => errors.push((reference.to_owned(), reason)),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+
+    if errors.is_empty() { Ok(out) } else { Err(errors) }
+}
+
+fn expand(reference: &str) -> Result<String, String> {
+    if reference.starts_with("file:") {
+        let path = &reference["file:".len()..];
+        return fs::read_to_string(path)
+            .map(|contents| contents.trim_end_matches(|c| c == '\n' || c == '\r').to_owned())
+            .map_err(|e| format!("failed to read {}: {}", path, e));
+    }
+
+    let (name, default) = match reference.find(":-") {
+        Some(at) => (&reference[..at], Some(&reference[at + 2..])),
+        None => (reference, None),
+    };
+    match (env::var(name), default) {
+        (Ok(value), _) => Ok(value),
+        (Err(_), Some(default)) => Ok(default.to_owned()),
+        (Err(env::VarError::NotPresent), None) => Err("not set".to_owned()),
+        (Err(env::VarError::NotUnicode(_)), None) => Err("not valid unicode".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_variables_are_substituted() {
+        env::set_var("VACCINE_TEST_TENANT", "7");
+        assert_eq!(interpolate("tenant-${VACCINE_TEST_TENANT}/${VACCINE_TEST_TENANT}"), Ok("tenant-7/7".to_owned()));
+        assert_eq!(interpolate("${VACCINE_TEST_TENANT:-1}-${VACCINE_TEST_UNSET:-local}"), Ok("7-local".to_owned()));
+        assert_eq!(interpolate("$${VACCINE_TEST_TENANT} costs $5"), Ok("${VACCINE_TEST_TENANT} costs $5".to_owned()));
+        assert_eq!(interpolate("no variables"), Ok("no variables".to_owned()));
+    }
+
+    #[test]
+    fn files_are_substituted() {
+        let path = env::temp_dir().join(format!("vaccine-interpolation-{}", std::process::id()));
+        fs::write(&path, "s3cret\n").unwrap();
+        assert_eq!(interpolate(&format!("Bearer ${{file:{}}}", path.display())), Ok("Bearer s3cret".to_owned()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn every_unresolved_variable_is_reported() {
+        assert_eq!(interpolate("${VACCINE_TEST_UNSET}/${VACCINE_TEST_UNSET_TOO}"), Err(vec![
+            ("VACCINE_TEST_UNSET".to_owned(), "not set".to_owned()),
+            ("VACCINE_TEST_UNSET_TOO".to_owned(), "not set".to_owned()),
+        ]));
+        assert_eq!(interpolate("${VACCINE"), Err(vec![("VACCINE".to_owned(), "no closing }".to_owned())]));
+
+        let error = interpolate_schema(&json!({
+            "root_url": "${VACCINE_TEST_UNSET_URL}",
+            "endpoints": [{ "headers": { "X-Tenant/Id": "${VACCINE_TEST_UNSET}" } }],
+            "profiles": { "staging": { "root_url": "${VACCINE_TEST_UNSET_STAGING_URL}" } }
+        })).unwrap_err();
+        assert_eq!(error.to_string(), "unresolved variables in the schema:\n  \
+                                       ${VACCINE_TEST_UNSET} at /endpoints/0/headers/X-Tenant~1Id: not set\n  \
+                                       ${VACCINE_TEST_UNSET_URL} at /root_url: not set");
+    }
+}
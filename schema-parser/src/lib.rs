@@ -5,8 +5,16 @@ extern crate serde_json;
 
 mod value_extractors;
 mod datetime_parser;
+mod interpolation;
+mod profiles;
+mod definitions;
 pub mod configuration;
-mod rest_endpoint;
-mod random_values;
+pub mod rest_endpoint;
+pub mod random_values;
 mod payload_item;
-mod dependency_resolver;
+pub mod dependency_resolver;
+pub mod expectations;
+pub mod response_schema;
+pub mod request_parameters;
+pub mod schema_file;
+pub mod validation;
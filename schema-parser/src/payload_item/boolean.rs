@@ -19,6 +19,10 @@ impl BooleanItem {
 }
 
 impl PayloadItem for BooleanItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn default_value(&self) -> (String, Value) {
         (self.name.clone(), Value::from(self.default_value))
     }
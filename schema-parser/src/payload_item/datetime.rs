@@ -1,6 +1,6 @@
 use serde_json::Value;
 
-use crate::datetime_parser::{datetime_from_now, is_now};
+use crate::datetime_parser::{datetime_from_now, is_now, parse_datetime};
 use crate::payload_item::ItemType;
 use crate::payload_item::PayloadItem;
 use crate::random_values::generate_random_datetime;
@@ -25,6 +25,10 @@ impl DatetimeItem {
 }
 
 impl PayloadItem for DatetimeItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn default_value(&self) -> (String, Value) {
         let value = if is_now(&self.default_value) {
             let d = datetime_from_now(&self.default_value, &self.timezone);
@@ -44,6 +48,15 @@ impl PayloadItem for DatetimeItem {
     fn item_type(&self) -> ItemType {
         ItemType::Datetime
     }
+
+    fn mismatches(&self, sent: &Value, stored: &Value) -> Vec<String> {
+        let parse = |v: &Value| v.as_str().and_then(|s| parse_datetime(s, &self.format, &self.timezone));
+        match (parse(sent), parse(stored)) {
+            (Some(a), Some(b)) if a == b => vec![],
+            _ if sent == stored => vec![],
+            _ => vec![self.name.clone()],
+        }
+    }
 }
 
 #[cfg(test)]
@@ -95,4 +108,12 @@ mod datetime_item {
         assert_eq!(name, "startTime");
         assert!(NaiveDate::parse_from_str(value.as_str().unwrap(), "%Y-%m-%d").is_ok());
     }
+
+    #[test]
+    fn test_datetime_item_mismatches_compare_instants() {
+        let d = create_datetime_item();
+        assert!(d.mismatches(&json!("2019-06-01"), &json!("2019-05-31T18:30:00+00:00")).is_empty());
+        assert_eq!(d.mismatches(&json!("2019-06-01"), &json!("2019-06-01T00:00:00Z")), vec!["startTime"]);
+        assert_eq!(d.mismatches(&json!("2019-06-01"), &json!(null)), vec!["startTime"]);
+    }
 }
@@ -19,6 +19,10 @@ impl FloatItem {
 }
 
 impl PayloadItem for FloatItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn default_value(&self) -> (String, Value) {
         (self.name.clone(), json!(self.default_value))
     }
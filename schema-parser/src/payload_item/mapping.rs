@@ -21,6 +21,10 @@ impl MappingItem {
 }
 
 impl PayloadItem for MappingItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn default_value(&self) -> (String, Value) {
         let default_values = self.children
             .iter()
@@ -42,6 +46,19 @@ impl PayloadItem for MappingItem {
     fn item_type(&self) -> ItemType {
         ItemType::Mapping
     }
+
+    fn mismatches(&self, sent: &Value, stored: &Value) -> Vec<String> {
+        if !stored.is_object() {
+            return vec![self.name.clone()];
+        }
+
+        self.children
+            .iter()
+            .filter_map(|child| sent.get(child.name()).map(|value| (child, value)))
+            .flat_map(|(child, value)| child.mismatches(value, &stored[child.name()]))
+            .map(|path| format!("{}.{}", self.name, path))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -83,4 +100,14 @@ mod mapping_item {
         assert!(value["snnum"].is_number());
         assert!(value["ss"].is_string());
     }
+
+    #[test]
+    fn test_mapping_item_mismatches_are_nested() {
+        let m = create_map_item();
+        let sent = json!({ "ss": "abc", "snnum": 1 });
+        assert!(m.mismatches(&sent, &json!({ "ss": "abc", "snnum": 1, "extra": true })).is_empty());
+        assert_eq!(m.mismatches(&sent, &json!({ "ss": "abc", "snnum": 2 })), vec!["sn.snnum"]);
+        assert_eq!(m.mismatches(&sent, &json!({ "ss": "abc" })), vec!["sn.snnum"]);
+        assert_eq!(m.mismatches(&sent, &json!(null)), vec!["sn"]);
+    }
 }
@@ -2,6 +2,7 @@ use std::fmt::{Debug, Error, Formatter};
 
 use serde_json::Value;
 
+use crate::random_values::{generate_random_number, generate_random_string};
 use crate::value_extractors::ValueExt;
 
 pub mod float;
@@ -11,6 +12,7 @@ pub mod mapping;
 pub mod boolean;
 pub mod datetime;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ItemType {
     Datetime,
     Boolean,
@@ -20,22 +22,62 @@ pub enum ItemType {
     Float,
 }
 
-pub trait PayloadItem {
+impl ItemType {
+    /// The `kind`s a schema entry can have.
+    pub const KINDS: [&'static str; 6] = ["string", "number", "float", "boolean", "datetime", "mapping"];
+
+    pub fn from_kind(kind: &str) -> Option<ItemType> {
+        match kind {
+            "string" => Some(ItemType::String),
+            "number" => Some(ItemType::Number),
+            "float" => Some(ItemType::Float),
+            "boolean" => Some(ItemType::Boolean),
+            "datetime" => Some(ItemType::Datetime),
+            "mapping" => Some(ItemType::Mapping),
+            _ => None,
+        }
+    }
+
+    /// The type of a schema entry, be it for a payload or a response.
+    pub fn of(v: &Value) -> ItemType {
+        let kind = v.get_string("kind");
+        ItemType::from_kind(&kind).unwrap_or_else(|| panic!("invalid type {}", kind))
+    }
+}
+
+pub trait PayloadItem: Send + Sync {
+    fn name(&self) -> &str;
     fn default_value(&self) -> (String, Value);
     fn random_value(&self) -> (String, Value);
     fn item_type(&self) -> ItemType;
+
+    /// A value of the wrong type for this item, which a server should refuse.
+    fn confused_value(&self) -> (String, Value) {
+        let value = match self.item_type() {
+            ItemType::String | ItemType::Datetime => json!(generate_random_number()),
+            _ => json!(generate_random_string()),
+        };
+        (self.name().to_owned(), value)
+    }
+
+    /// Returns the paths of the fields which `stored` does not hold as they were `sent`.
+    fn mismatches(&self, sent: &Value, stored: &Value) -> Vec<String> {
+        if sent == stored {
+            vec![]
+        } else {
+            vec![self.name().to_owned()]
+        }
+    }
 }
 
 pub fn payload_item_from_json(v: &Value) -> Box<dyn PayloadItem> {
-    let kind = v.get_string("kind");
-    match kind.as_str() {
-        "string" => Box::new(string::StringItem::new(&v)),
-        "number" => Box::new(number::NumberItem::new(&v)),
-        "float" => Box::new(float::FloatItem::new(&v)),
-        "datetime" => Box::new(datetime::DatetimeItem::new(&v)),
-        "mapping" => Box::new(mapping::MappingItem::new(&v)),
-        "boolean" => Box::new(boolean::BooleanItem::new(&v)),
-        _ => panic!(format!("invalid type {}", kind.as_str()))
+    match ItemType::of(v) {
+        ItemType::String => Box::new(string::StringItem::new(v)),
+        ItemType::Number => Box::new(number::NumberItem::new(v)),
+        ItemType::Float => Box::new(float::FloatItem::new(v)),
+        ItemType::Datetime => Box::new(datetime::DatetimeItem::new(v)),
+        ItemType::Mapping => Box::new(mapping::MappingItem::new(v)),
+        ItemType::Boolean => Box::new(boolean::BooleanItem::new(v)),
     }
 }
 
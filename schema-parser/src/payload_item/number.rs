@@ -19,6 +19,10 @@ impl NumberItem {
 }
 
 impl PayloadItem for NumberItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn default_value(&self) -> (String, Value) {
         (self.name.clone(), json!(self.default_value))
     }
@@ -20,6 +20,10 @@ impl StringItem {
 }
 
 impl PayloadItem for StringItem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
     fn default_value(&self) -> (String, Value) {
         (self.name.clone(), json!(self.default_value))
     }
@@ -0,0 +1,114 @@
+use serde_json::{Map, Value};
+
+/// Returns the schema `v` with the named entry of its `profiles` applied.
+///
+/// A profile replaces any top level field it sets, such as `root_url` or `auth_type`, and its
+/// `defaults` replace the `default_value` of schema entries, keyed by endpoint name and then by
+/// field name. Fields nested in a mapping are named by their path, like `constructionMaterial.tonnage`.
+pub fn apply_profile(v: &Value, name: &str) -> Result<Value, String> {
+    let profiles = v.get("profiles")
+        .ok_or("the schema has no `profiles`")?
+        .as_object()
+        .ok_or("`profiles` is not an object")?;
+    let profile = profiles.get(name)
+        .ok_or_else(|| {
+            let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            format!("no profile `{}`, the schema has {}", name, known.join(", "))
+        })?
+        .as_object()
+        .ok_or_else(|| format!("profile `{}` is not an object", name))?;
+
+    let mut v = v.clone();
+    for (field, value) in profile {
+        match field.as_str() {
+            "defaults" => apply_defaults(&mut v, name, value)?,
+            "endpoints" | "profiles" => return Err(format!("profile `{}` cannot override `{}`", name, field)),
+            _ => { v[field] = value.clone(); }
+        }
+    }
+    Ok(v)
+}
+
+fn apply_defaults(v: &mut Value, profile: &str, defaults: &Value) -> Result<(), String> {
+    let defaults = defaults.as_object()
+        .ok_or_else(|| format!("`defaults` in profile `{}` is not an object", profile))?;
+
+    for (endpoint_name, fields) in defaults {
+        let endpoint = v["endpoints"].as_array_mut()
+            .and_then(|endpoints| endpoints.iter_mut().find(|e| e["name"] == endpoint_name.as_str()))
+            .ok_or_else(|| format!("profile `{}` sets defaults for unknown endpoint {}", profile, endpoint_name))?;
+        let fields = fields.as_object()
+            .ok_or_else(|| format!("defaults for {} in profile `{}` are not an object", endpoint_name, profile))?;
+
+        for (path, default_value) in fields {
+            let item = find_item(&mut endpoint["schema"], path)
+                .ok_or_else(|| format!("profile `{}` sets a default for unknown field {}.{}", profile, endpoint_name, path))?;
+            item.insert("default_value".to_owned(), default_value.clone());
+        }
+    }
+    Ok(())
+}
+
+/// The schema entry at the dotted `path`, descending through mappings.
+fn find_item<'a>(schema: &'a mut Value, path: &str) -> Option<&'a mut Map<String, Value>> {
+    let (name, rest) = match path.find('.') {
+        Some(dot) => (&path[..dot], Some(&path[dot + 1..])),
+        None => (path, None),
+    };
+    let item = schema.as_array_mut()?
+        .iter_mut()
+        .find(|item| item["name"] == name)?;
+    match rest {
+        Some(rest) => find_item(&mut item["schema"], rest),
+        None => item.as_object_mut(),
+    }
+}
+
+#[cfg(test)]
+mod profiles {
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "root_url": "http://localhost:8000",
+            "auth_type": "Basic",
+            "endpoints": [
+                { "name": "house", "url": "/houses", "requires": [], "schema": [
+                    { "name": "houseType", "kind": "string", "default_value": "castle" },
+                    { "name": "constructionMaterial", "kind": "mapping", "schema": [
+                        { "name": "tonnage", "kind": "number", "default_value": 100 }
+                    ] }
+                ] }
+            ],
+            "profiles": {
+                "staging": {
+                    "root_url": "https://staging.example.com",
+                    "auth_type": "Bearer",
+                    "defaults": {
+                        "house": { "houseType": "fort", "constructionMaterial.tonnage": 5 }
+                    }
+                },
+                "broken": { "defaults": { "house": { "constructionMaterial.walls": 1 } } }
+            }
+        })
+    }
+
+    #[test]
+    fn profiles_override_fields_and_defaults() {
+        let v = apply_profile(&schema(), "staging").unwrap();
+        assert_eq!(v["root_url"], "https://staging.example.com");
+        assert_eq!(v["auth_type"], "Bearer");
+
+        let items = &v["endpoints"][0]["schema"];
+        assert_eq!(items[0]["default_value"], "fort");
+        assert_eq!(items[1]["schema"][0]["default_value"], 5);
+        assert_eq!(schema()["endpoints"][0]["schema"][0]["default_value"], "castle");
+    }
+
+    #[test]
+    fn unknown_profiles_and_fields_are_refused() {
+        assert_eq!(apply_profile(&schema(), "prod"), Err("no profile `prod`, the schema has broken, staging".to_owned()));
+        assert_eq!(apply_profile(&schema(), "broken"),
+                   Err("profile `broken` sets a default for unknown field house.constructionMaterial.walls".to_owned()));
+    }
+}
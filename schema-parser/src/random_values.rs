@@ -1,52 +1,81 @@
-use chrono::Utc;
+use std::cell::RefCell;
+
+use chrono::{DateTime, TimeZone, Utc};
 use chrono_tz::Tz;
 use rand::distributions::Alphanumeric;
-use rand::Rng;
+use rand::{FromEntropy, Rng, SeedableRng};
+use rand::rngs::StdRng;
 use rand::seq::{SliceChooseIter, SliceRandom};
 
+struct Generator {
+    rng: StdRng,
+    /// What random datetimes are offset from, the current time unless seeded.
+    anchor: Option<DateTime<Utc>>,
+}
+
+thread_local! {
+    static GENERATOR: RefCell<Generator> = RefCell::new(Generator { rng: StdRng::from_entropy(), anchor: None });
+}
+
+/// Makes the values generated on this thread from now on the same for the same seed. Random datetimes
+/// are then offset from 2000-01-01 rather than the current time.
+pub fn seed(seed: u64) {
+    GENERATOR.with(|g| *g.borrow_mut() = Generator {
+        rng: StdRng::seed_from_u64(seed),
+        anchor: Some(Utc.timestamp_opt(946_684_800, 0).unwrap()),
+    });
+}
+
+fn with_rng<T, F: FnOnce(&mut StdRng) -> T>(f: F) -> T {
+    GENERATOR.with(|g| f(&mut g.borrow_mut().rng))
+}
+
 pub fn generate_random_string() -> String {
-    let mut r = rand::thread_rng();
-    let size = r.gen_range(5, 20);
-    r.sample_iter(&Alphanumeric)
-        .take(size)
-        .collect()
+    with_rng(|r| {
+        let size = r.gen_range(5, 20);
+        r.sample_iter(&Alphanumeric)
+            .take(size)
+            .collect()
+    })
 }
 
 pub fn generate_random_number() -> i64 {
-    let mut r = rand::thread_rng();
-    r.gen()
+    with_rng(|r| r.gen())
 }
 
 pub fn generate_random_float() -> f64 {
-    let mut r = rand::thread_rng();
-    r.gen()
+    with_rng(|r| r.gen())
 }
 
 pub fn generate_random_boolean() -> bool {
-    let mut r = rand::thread_rng();
-    r.gen::<bool>()
+    with_rng(|r| r.gen::<bool>())
+}
+
+/// True with the given probability, between 0 and 1.
+pub fn generate_weighted_boolean(probability: f64) -> bool {
+    with_rng(|r| r.gen_bool(probability))
 }
 
-fn generate_number_in_range(start: i64, stop: i64) -> i64 {
-    let mut r = rand::thread_rng();
-    r.gen_range(start, stop)
+pub fn generate_number_in_range(start: i64, stop: i64) -> i64 {
+    with_rng(|r| r.gen_range(start, stop))
 }
 
 pub fn generate_random_datetime(format_string: &str, timezone: &str) -> String {
     let tz: Tz = timezone.parse().expect("failed to parse timezone!");
-    let now = Utc::now().with_timezone(&tz);
+    let anchor = GENERATOR.with(|g| g.borrow().anchor).unwrap_or_else(Utc::now).with_timezone(&tz);
     let delta = chrono::Duration::days(generate_number_in_range(-500000, 50000));
-    let random_datetime = now + delta;
+    let random_datetime = anchor + delta;
     random_datetime.format(format_string).to_string()
 }
 
+/// At least one of `items`, unless there are none.
 pub fn random_elements<T>(items: &Vec<T>) -> SliceChooseIter<[T], T> {
-    let mut r = rand::thread_rng();
-    let count = r.gen_range(1, items.len() + 1);
-    items.choose_multiple(&mut r, count)
+    with_rng(|r| {
+        let count = if items.is_empty() { 0 } else { r.gen_range(1, items.len() + 1) };
+        items.choose_multiple(r, count)
+    })
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,6 +89,22 @@ mod tests {
                 .collect();
             assert!(p.len() >= 1 && p.len() <= names.len());
         }
+        assert_eq!(random_elements::<&str>(&vec![]).count(), 0);
+    }
+
+    #[test]
+    fn seeded_values_repeat() {
+        let generate = || (
+            generate_random_string(),
+            generate_random_number(),
+            generate_random_datetime("%Y-%m-%d %H:%M:%S%.f", "UTC"),
+        );
+        seed(7);
+        let first = generate();
+        seed(7);
+        assert_eq!(generate(), first);
+        seed(8);
+        assert_ne!(generate(), first);
     }
 
     #[test]
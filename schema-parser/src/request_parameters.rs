@@ -0,0 +1,215 @@
+use serde_json::{Map, Value};
+
+use rest_client::request_options::RequestOptions;
+
+use crate::payload_item::{payload_item_from_json, PayloadItem};
+
+#[derive(Debug)]
+pub enum ParameterValue {
+    Fixed(String),
+    /// Generated like a payload field, taking its default value unless randomized.
+    Fuzzed(Box<dyn PayloadItem>),
+}
+
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub value: ParameterValue,
+    /// The fixed value as written in the schema, when it was expanded from `${...}`.
+    pub template: Option<String>,
+}
+
+impl Parameter {
+    /// A string value is sent as is, its `${ENV}`s having been expanded with the rest of the session.
+    /// An object is read like a schema entry named after the parameter and makes the parameter fuzzable.
+    pub fn new(name: &str, v: &Value) -> Self {
+        let value = match v {
+            Value::String(s) => ParameterValue::Fixed(s.clone()),
+            Value::Number(_) | Value::Bool(_) => ParameterValue::Fixed(v.to_string()),
+            Value::Object(spec) => {
+                let mut spec = spec.clone();
+                spec.insert("name".to_owned(), Value::from(name));
+                ParameterValue::Fuzzed(payload_item_from_json(&Value::Object(spec)))
+            }
+            _ => panic!("parameter {} is not a string, number, boolean or schema entry", name),
+        };
+        Parameter { name: name.to_owned(), value, template: None }
+    }
+
+    pub fn value(&self, randomized: bool) -> String {
+        let item = match &self.value {
+            ParameterValue::Fixed(value) => return value.clone(),
+            ParameterValue::Fuzzed(item) => item,
+        };
+        let (_, value) = if randomized { item.random_value() } else { item.default_value() };
+        match value {
+            Value::String(s) => s,
+            other => other.to_string(),
+        }
+    }
+
+    pub fn is_fuzzed(&self) -> bool {
+        match self.value {
+            ParameterValue::Fixed(_) => false,
+            ParameterValue::Fuzzed(_) => true,
+        }
+    }
+}
+
+/// The headers, query parameters and cookies declared by a session or an endpoint.
+#[derive(Debug, Default)]
+pub struct RequestParameters {
+    pub headers: Vec<Parameter>,
+    pub query: Vec<Parameter>,
+    pub cookies: Vec<Parameter>,
+}
+
+impl RequestParameters {
+    /// Reads the `headers`, `query` and `cookies` objects of `v`, each of which is optional.
+    pub fn new(v: &Value) -> Self {
+        let parameters = |key: &str| -> Vec<Parameter> {
+            v.get(key)
+                .map(|p| p.as_object().expect(&format!("`{}` is not an object", key)))
+                .unwrap_or(&Map::new())
+                .iter()
+                .map(|(name, value)| Parameter::new(name, value))
+                .collect()
+        };
+
+        RequestParameters {
+            headers: parameters("headers"),
+            query: parameters("query"),
+            cookies: parameters("cookies"),
+        }
+    }
+
+    /// Remembers the templates of fixed values which differ from the same parameters in `raw`, the
+    /// schema before its `${...}`s were expanded.
+    pub fn remember_templates(&mut self, raw: &Value) {
+        for (key, parameters) in [("headers", &mut self.headers), ("query", &mut self.query), ("cookies", &mut self.cookies)].iter_mut() {
+            for p in parameters.iter_mut() {
+                if let (ParameterValue::Fixed(value), Some(Value::String(template))) = (&p.value, raw[*key].get(&p.name)) {
+                    if template != value {
+                        p.template = Some(template.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// `options` as they are written to reports, with any value expanded from a template put back as the
+    /// template, so that secrets read from the environment or files do not end up in them.
+    pub fn recorded(&self, options: &RequestOptions) -> RequestOptions {
+        let mut recorded = options.clone();
+        restore_templates(&mut recorded.headers, &self.headers, true);
+        restore_templates(&mut recorded.query, &self.query, false);
+        restore_templates(&mut recorded.cookies, &self.cookies, false);
+        recorded
+    }
+
+    pub fn is_fuzzed(&self) -> bool {
+        self.headers.iter().chain(&self.query).chain(&self.cookies).any(Parameter::is_fuzzed)
+    }
+
+    pub fn default_options(&self) -> RequestOptions {
+        self.options(false)
+    }
+
+    /// The options to send with a fuzzed request, with random values for the fuzzable parameters.
+    pub fn randomized_options(&self) -> RequestOptions {
+        self.options(true)
+    }
+
+    fn options(&self, randomized: bool) -> RequestOptions {
+        let mut options = RequestOptions::new();
+        for p in &self.headers {
+            options = options.header(&p.name, &p.value(randomized));
+        }
+        for p in &self.query {
+            options = options.query(&p.name, &p.value(randomized));
+        }
+        for p in &self.cookies {
+            options = options.cookie(&p.name, &p.value(randomized));
+        }
+        options
+    }
+}
+
+fn restore_templates(pairs: &mut [(String, String)], parameters: &[Parameter], lowercase_names: bool) {
+    for (name, value) in pairs.iter_mut() {
+        let template = parameters.iter()
+            .filter(|p| if lowercase_names { p.name.to_lowercase() == *name } else { p.name == *name })
+            .find_map(|p| match (&p.value, &p.template) {
+                (ParameterValue::Fixed(expanded), Some(template)) if expanded == value => Some(template),
+                _ => None,
+            });
+        if let Some(template) = template {
+            *value = template.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_and_fuzzed_parameters() {
+        let parameters = RequestParameters::new(&json!({
+            "headers": {
+                "X-Tenant-Id": "42",
+                "X-Api-Version": { "kind": "number", "default_value": 2 }
+            },
+            "query": { "dry_run": false },
+            "cookies": { "theme": "dark" }
+        }));
+        assert!(parameters.is_fuzzed());
+
+        let options = parameters.default_options();
+        assert_eq!(options.headers, vec![
+            ("x-api-version".to_owned(), "2".to_owned()),
+            ("x-tenant-id".to_owned(), "42".to_owned()),
+        ]);
+        assert_eq!(options.query, vec![("dry_run".to_owned(), "false".to_owned())]);
+        assert_eq!(options.cookies, vec![("theme".to_owned(), "dark".to_owned())]);
+
+        let options = parameters.randomized_options();
+        assert!(options.headers[0].1.parse::<i64>().is_ok());
+        assert_eq!(options.headers[1].1, "42");
+    }
+
+    #[test]
+    fn expanded_values_are_recorded_as_templates() {
+        let raw = json!({
+            "headers": { "Authorization": "Bearer ${TOKEN}", "X-Tenant-Id": "42" },
+            "cookies": { "session": "${file:session.txt}" }
+        });
+        let mut parameters = RequestParameters::new(&json!({
+            "headers": { "Authorization": "Bearer s3cret", "X-Tenant-Id": "42" },
+            "cookies": { "session": "abc" }
+        }));
+        parameters.remember_templates(&raw);
+
+        let options = parameters.default_options().merged_with(&RequestOptions::new().cookie("theme", "dark"));
+        let recorded = parameters.recorded(&options);
+        assert_eq!(recorded.headers, vec![
+            ("authorization".to_owned(), "Bearer ${TOKEN}".to_owned()),
+            ("x-tenant-id".to_owned(), "42".to_owned()),
+        ]);
+        assert_eq!(recorded.cookies, vec![
+            ("session".to_owned(), "${file:session.txt}".to_owned()),
+            ("theme".to_owned(), "dark".to_owned()),
+        ]);
+
+        // Overridden by a value which was not expanded
+        let options = parameters.default_options().merged_with(&RequestOptions::new().header("Authorization", "none"));
+        assert_eq!(parameters.recorded(&options).headers[0].1, "none");
+    }
+
+    #[test]
+    fn parameters_are_optional() {
+        let parameters = RequestParameters::new(&json!({ "name": "castle" }));
+        assert!(!parameters.is_fuzzed());
+        assert!(parameters.default_options().is_empty());
+    }
+}
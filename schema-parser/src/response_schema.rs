@@ -0,0 +1,198 @@
+use serde_json::Value;
+
+use crate::datetime_parser::parse_datetime;
+use crate::payload_item::ItemType;
+use crate::value_extractors::ValueExt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    String,
+    Number,
+    Float,
+    Boolean,
+    Datetime { format: String, timezone: String },
+    Mapping(ResponseSchema),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseField {
+    pub name: String,
+    pub kind: FieldKind,
+    pub optional: bool,
+}
+
+impl ResponseField {
+    pub fn new(v: &Value) -> Self {
+        let kind = match ItemType::of(v) {
+            ItemType::String => FieldKind::String,
+            ItemType::Number => FieldKind::Number,
+            ItemType::Float => FieldKind::Float,
+            ItemType::Boolean => FieldKind::Boolean,
+            ItemType::Datetime => FieldKind::Datetime {
+                format: v.get_string("format"),
+                timezone: v.get_string("timezone"),
+            },
+            ItemType::Mapping => FieldKind::Mapping(ResponseSchema::new(&v["schema"])),
+        };
+
+        ResponseField {
+            name: v.get_string("name"),
+            kind,
+            optional: v.get("optional").and_then(Value::as_bool).unwrap_or(false),
+        }
+    }
+
+    fn validate(&self, path: &str, v: &Value, problems: &mut Vec<String>) {
+        let valid = match &self.kind {
+            FieldKind::String => v.is_string(),
+            FieldKind::Number => v.is_i64() || v.is_u64(),
+            FieldKind::Float => v.is_number(),
+            FieldKind::Boolean => v.is_boolean(),
+            FieldKind::Datetime { format, timezone } => v.as_str()
+                .and_then(|s| parse_datetime(s, format, timezone))
+                .is_some(),
+            FieldKind::Mapping(schema) => {
+                if v.is_object() {
+                    schema.validate_at(&format!("{}.", path), v, problems);
+                }
+                v.is_object()
+            }
+        };
+
+        if !valid {
+            problems.push(format!("{}: expected {}, got {}", path, self.kind_name(), v));
+        }
+    }
+
+    fn kind_name(&self) -> &'static str {
+        match self.kind {
+            FieldKind::String => "string",
+            FieldKind::Number => "number",
+            FieldKind::Float => "float",
+            FieldKind::Boolean => "boolean",
+            FieldKind::Datetime { .. } => "datetime",
+            FieldKind::Mapping(_) => "mapping",
+        }
+    }
+}
+
+/// The shape of a response body, written in the same format as an endpoint's `schema`
+/// except that `default_value`s are not needed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseSchema {
+    pub fields: Vec<ResponseField>,
+    pub allow_extra_fields: bool,
+}
+
+impl ResponseSchema {
+    /// Accepts either a bare array of fields or an object with `fields` and `allow_extra_fields`.
+    pub fn new(v: &Value) -> Self {
+        let (fields, allow_extra_fields) = if v.is_object() {
+            (&v["fields"], v.get("allow_extra_fields").and_then(Value::as_bool).unwrap_or(false))
+        } else {
+            (v, false)
+        };
+
+        let fields = fields.as_array()
+            .expect("response schema is not an array")
+            .iter()
+            .map(ResponseField::new)
+            .collect();
+        ResponseSchema { fields, allow_extra_fields }
+    }
+
+    /// Returns every missing field, field of the wrong type and unexpected extra field in `body`.
+    pub fn validate(&self, body: &Value) -> Vec<String> {
+        let mut problems = vec![];
+        if body.is_object() {
+            self.validate_at("", body, &mut problems);
+        } else {
+            problems.push(format!("expected an object, got {}", body));
+        }
+        problems
+    }
+
+    fn validate_at(&self, prefix: &str, body: &Value, problems: &mut Vec<String>) {
+        let body = body.as_object().unwrap();
+
+        for field in &self.fields {
+            let path = format!("{}{}", prefix, field.name);
+            match body.get(&field.name) {
+                None if !field.optional => problems.push(format!("{}: missing", path)),
+                None => {}
+                Some(Value::Null) if field.optional => {}
+                Some(v) => field.validate(&path, v, problems),
+            }
+        }
+
+        if !self.allow_extra_fields {
+            for name in body.keys().filter(|k| !self.fields.iter().any(|f| &f.name == *k)) {
+                problems.push(format!("{}{}: unexpected field", prefix, name));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_schema() -> ResponseSchema {
+        ResponseSchema::new(&json!([
+            { "name": "id", "kind": "number" },
+            { "name": "houseType", "kind": "string" },
+            { "name": "nickname", "kind": "string", "optional": true },
+            { "name": "startDate", "kind": "datetime", "timezone": "Asia/Kolkata", "format": "%Y-%m-%d" },
+            { "name": "material", "kind": "mapping", "schema": [
+                { "name": "tonnage", "kind": "float" },
+                { "name": "flammable", "kind": "boolean" }
+            ] }
+        ]))
+    }
+
+    #[test]
+    fn valid_body_has_no_problems() {
+        let body = json!({
+            "id": 1,
+            "houseType": "castle",
+            "startDate": "2019-01-01",
+            "material": { "tonnage": 1.5, "flammable": false }
+        });
+        assert!(create_schema().validate(&body).is_empty());
+    }
+
+    #[test]
+    fn problems_are_reported_with_paths() {
+        let body = json!({
+            "id": "1",
+            "nickname": null,
+            "startDate": "yesterday",
+            "material": { "tonnage": 1, "flammable": "no", "colour": "red" },
+            "owner": "me"
+        });
+        assert_eq!(create_schema().validate(&body), vec![
+            "id: expected number, got \"1\"",
+            "houseType: missing",
+            "startDate: expected datetime, got \"yesterday\"",
+            "material.flammable: expected boolean, got \"no\"",
+            "material.colour: unexpected field",
+            "owner: unexpected field",
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid type text")]
+    fn kinds_are_parsed_as_for_payloads() {
+        ResponseSchema::new(&json!([{ "name": "id", "kind": "text" }]));
+    }
+
+    #[test]
+    fn extra_fields_can_be_allowed() {
+        let schema = ResponseSchema::new(&json!({
+            "allow_extra_fields": true,
+            "fields": [{ "name": "id", "kind": "number" }]
+        }));
+        assert!(schema.validate(&json!({ "id": 1, "other": true })).is_empty());
+        assert_eq!(schema.validate(&json!([])), vec!["expected an object, got []"]);
+    }
+}
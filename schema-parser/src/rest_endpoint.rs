@@ -1,7 +1,10 @@
 use serde_json::{Map, Value};
 
+use crate::expectations::{Expectations, MutationClass};
 use crate::payload_item::{payload_item_from_json, PayloadItem};
-use crate::random_values::random_elements;
+use crate::request_parameters::RequestParameters;
+use crate::random_values::{generate_number_in_range, random_elements};
+use crate::response_schema::ResponseSchema;
 use crate::value_extractors::ValueExt;
 
 #[derive(Debug)]
@@ -10,6 +13,12 @@ pub struct Endpoint {
     pub url: String,
     pub requires: Vec<String>,
     pub components: Vec<Box<dyn PayloadItem>>,
+    pub expectations: Expectations,
+    pub response_schema: Option<ResponseSchema>,
+    pub delete_url: Option<String>,
+    /// How many fuzzing sequences this endpoint gets relative to the others, zero to skip it.
+    pub weight: u32,
+    pub parameters: RequestParameters,
 }
 
 impl Endpoint {
@@ -33,7 +42,22 @@ impl Endpoint {
             .map(payload_item_from_json)
             .collect::<Vec<Box<dyn PayloadItem>>>();
 
-        Endpoint { name, url, requires, components }
+        let expectations = v.get("expectations")
+            .map(Expectations::new)
+            .unwrap_or_default();
+
+        let response_schema = v.get("response_schema").map(ResponseSchema::new);
+
+        let delete_url = v.get("delete_url")
+            .map(|u| u.as_str().expect("`delete_url` is not a string").to_string());
+
+        let weight = v.get("weight")
+            .map(|w| w.as_u64().expect("`weight` is not a non negative integer") as u32)
+            .unwrap_or(1);
+
+        let parameters = RequestParameters::new(v);
+
+        Endpoint { name, url, requires, components, expectations, response_schema, delete_url, weight, parameters }
     }
 
     pub fn default_payload(&self) -> Value {
@@ -49,6 +73,69 @@ impl Endpoint {
             .collect::<Map<String, Value>>();
         Value::from(m)
     }
+
+    /// A randomized payload in which one of the chosen fields has a value of the wrong type, or an
+    /// empty one if the endpoint has no fields.
+    pub fn type_confused_payload(&self) -> Value {
+        let chosen: Vec<&Box<dyn PayloadItem>> = random_elements(&self.components).collect();
+        if chosen.is_empty() {
+            return Value::from(Map::new());
+        }
+        let confused = generate_number_in_range(0, chosen.len() as i64) as usize;
+        let m = chosen.iter()
+            .enumerate()
+            .map(|(i, c)| if i == confused { c.confused_value() } else { c.random_value() })
+            .collect::<Map<String, Value>>();
+        Value::from(m)
+    }
+
+    pub fn payload_for(&self, class: MutationClass) -> Value {
+        match class {
+            MutationClass::Valid => self.randomized_payload(),
+            MutationClass::TypeConfused => self.type_confused_payload(),
+        }
+    }
+
+    /// Returns the paths of the fields in the `sent` payload which the `stored` object does not hold.
+    pub fn mismatches(&self, sent: &Value, stored: &Value) -> Vec<String> {
+        self.components.iter()
+            .filter_map(|c| sent.get(c.name()).map(|value| (c, value)))
+            .flat_map(|(c, value)| c.mismatches(value, &stored[c.name()]))
+            .collect()
+    }
+
+    pub fn object_url(&self, created: &Value) -> String {
+        let id = match &created["id"] {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            _ => panic!("created object for {} has no usable `id`", self.name),
+        };
+        format!("{}/{}", self.url.trim_end_matches('/'), id)
+    }
+
+    /// Fills the `{field}` placeholders in `delete_url` from the created object, falling
+    /// back to the object's own url.
+    pub fn delete_url(&self, created: &Value) -> String {
+        match &self.delete_url {
+            None => self.object_url(created),
+            Some(template) => fill_template(template, created),
+        }
+    }
+}
+
+fn fill_template(template: &str, values: &Value) -> String {
+    let mut filled = template.to_owned();
+    if let Some(values) = values.as_object() {
+        for (key, value) in values {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                _ => continue,
+            };
+            filled = filled.replace(&format!("{{{}}}", key), &value);
+        }
+    }
+    filled
 }
 
 #[cfg(test)]
@@ -113,6 +200,7 @@ mod public_api {
         assert_eq!(e.url, "http://localhost:8000/api/v2/house");
         assert_eq!(e.requires.len(), 0);
         assert_eq!(e.components.len(), 6);
+        assert_eq!(e.weight, 1);
     }
 
     #[test]
@@ -175,4 +263,69 @@ mod public_api {
             material.get("tonnage").map(|v| v.is_number());
         });
     }
+
+    #[test]
+    fn type_confused_payload_generation() {
+        let e = create_endpoint();
+        let expected = e.default_payload();
+
+        for _ in 0..20 {
+            let p = e.type_confused_payload();
+            let p = p.as_object().unwrap();
+            let confused = p.iter()
+                .filter(|(name, value)| !same_type(value, &expected[name.as_str()]))
+                .count();
+            assert_eq!(confused, 1);
+        }
+    }
+
+    #[test]
+    fn endpoints_without_fields_get_empty_payloads() {
+        let e = Endpoint::new(&json!({ "name": "a", "url": "/a", "requires": [], "schema": [] }));
+        assert_eq!(e.randomized_payload(), json!({}));
+        assert_eq!(e.type_confused_payload(), json!({}));
+    }
+
+    fn same_type(a: &Value, b: &Value) -> bool {
+        a.is_string() == b.is_string() && a.is_number() == b.is_number()
+            && a.is_boolean() == b.is_boolean() && a.is_object() == b.is_object()
+    }
+
+    #[test]
+    fn stored_payload_mismatches() {
+        let e = create_endpoint();
+        let sent = json!({
+            "houseType": "hut",
+            "startDate": "2001-01-01 11:22:33",
+            "constructionMaterial": { "wallMaterial": "mud", "tonnage": 1, "flammable": true },
+        });
+        let mut stored = json!({
+            "id": 1,
+            "houseType": "hut",
+            "sizeInSquareFeet": 10,
+            "startDate": "2001-01-01T05:52:33Z",
+            "constructionMaterial": { "wallMaterial": "mud", "tonnage": 1, "flammable": true },
+        });
+        assert!(e.mismatches(&sent, &stored).is_empty());
+
+        stored["houseType"] = json!("castle");
+        stored["constructionMaterial"]["flammable"] = json!(false);
+        assert_eq!(e.mismatches(&sent, &stored), vec!["houseType", "constructionMaterial.flammable"]);
+    }
+
+    #[test]
+    fn object_url_from_created_id() {
+        let e = create_endpoint();
+        assert_eq!(e.object_url(&json!({"id": 12})), "http://localhost:8000/api/v2/house/12");
+        assert_eq!(e.object_url(&json!({"id": "abc"})), "http://localhost:8000/api/v2/house/abc");
+    }
+
+    #[test]
+    fn delete_url_from_template() {
+        let mut e = create_endpoint();
+        assert_eq!(e.delete_url(&json!({"id": 12})), "http://localhost:8000/api/v2/house/12");
+
+        e.delete_url = Some("/api/v2/house/{houseType}/{id}/".to_owned());
+        assert_eq!(e.delete_url(&json!({"id": 12, "houseType": "castle"})), "/api/v2/house/castle/12/");
+    }
 }
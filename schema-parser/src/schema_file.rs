@@ -0,0 +1,325 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Error, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl SchemaFormat {
+    /// The format named by the file extension, if it is one of `.json`, `.yaml`, `.yml` or `.toml`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(SchemaFormat::Json),
+            "yaml" | "yml" => Some(SchemaFormat::Yaml),
+            "toml" => Some(SchemaFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the first line which is not blank or a comment: JSON starts with `{`,
+    /// TOML with a `[table]` or `key = value`, and anything else is read as YAML.
+    pub fn detect(contents: &str) -> Self {
+        let first = contents.lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or("");
+
+        if first.starts_with('{') {
+            return SchemaFormat::Json;
+        }
+        let is_assignment = match (first.find('='), first.find(':')) {
+            (Some(equals), Some(colon)) => equals < colon,
+            (Some(_), None) => true,
+            _ => false,
+        };
+        if first.starts_with('[') || is_assignment {
+            SchemaFormat::Toml
+        } else {
+            SchemaFormat::Yaml
+        }
+    }
+}
+
+impl Display for SchemaFormat {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        f.write_str(match self {
+            SchemaFormat::Json => "JSON",
+            SchemaFormat::Yaml => "YAML",
+            SchemaFormat::Toml => "TOML",
+        })
+    }
+}
+
+/// A schema file which could not be read, with the 1-based line and column of the problem when known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}:{}: {}", self.path, line, column, self.message),
+            (Some(line), None) => write!(f, "{}:{}: {}", self.path, line, self.message),
+            _ => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl SchemaError {
+    fn new(path: &Path, message: String) -> Self {
+        SchemaError { path: path.display().to_string(), line: None, column: None, message }
+    }
+}
+
+/// Reads a JSON, YAML or TOML schema into the JSON value `Session::new` takes.
+pub fn read_schema(path: &str) -> Result<Value, SchemaError> {
+    read(Path::new(path))
+}
+
+fn read(path: &Path) -> Result<Value, SchemaError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| SchemaError::new(path, format!("failed to read: {}", e)))?;
+
+    let format = SchemaFormat::from_path(path).unwrap_or_else(|| SchemaFormat::detect(&contents));
+    parse_schema(&contents, format).map_err(|e| SchemaError { path: path.display().to_string(), ..e })
+}
+
+/// Reads a schema along with the files named by its `include`, a path or list of paths relative to it.
+///
+/// Included endpoints come before the schema's own, and its own definitions and other top level fields
+/// win over included ones of the same name. A file included more than once is only read the first time.
+pub fn load_schema(path: &str) -> Result<Value, SchemaError> {
+    load(Path::new(path), &mut Vec::new(), &mut HashSet::new())
+}
+
+fn load(path: &Path, including: &mut Vec<(PathBuf, String)>, loaded: &mut HashSet<PathBuf>)
+        -> Result<Value, SchemaError> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if including.iter().any(|(p, _)| *p == canonical) {
+        let chain: Vec<&str> = including.iter().map(|(_, shown)| shown.as_str()).collect();
+        return Err(SchemaError::new(path, format!("include cycle: {} -> {}", chain.join(" -> "), path.display())));
+    }
+    if !loaded.insert(canonical.clone()) {
+        return Ok(Value::Object(Map::new()));
+    }
+
+    let mut v = read(path)?;
+    let includes = match v.as_object_mut().and_then(|fields| fields.remove("include")) {
+        Some(includes) => includes,
+        None => return Ok(v),
+    };
+    let includes: Vec<String> = match includes {
+        Value::String(include) => vec![include],
+        Value::Array(ref includes) if includes.iter().all(Value::is_string) => {
+            includes.iter().map(|i| i.as_str().unwrap().to_owned()).collect()
+        }
+        _ => return Err(SchemaError::new(path, "`include` is not a path or a list of paths".to_owned())),
+    };
+
+    including.push((canonical, path.display().to_string()));
+    let mut merged = Value::Object(Map::new());
+    for include in includes {
+        let included = load(&path.parent().unwrap_or_else(|| Path::new("")).join(include), including, loaded)?;
+        merge_schema(&mut merged, included);
+    }
+    including.pop();
+
+    merge_schema(&mut merged, v);
+    Ok(merged)
+}
+
+/// Merges `other` into `schema`, appending its endpoints and letting its other fields win.
+fn merge_schema(schema: &mut Value, other: Value) {
+    if let Value::Object(fields) = other {
+        for (name, value) in fields {
+            match (name.as_str(), schema.get_mut(&name), value) {
+                ("endpoints", Some(Value::Array(endpoints)), Value::Array(more)) => endpoints.extend(more),
+                ("definitions", Some(Value::Object(definitions)), Value::Object(more)) => definitions.extend(more),
+                (_, _, value) => { schema[&name] = value; }
+            }
+        }
+    }
+}
+
+/// Parses `contents` as `format`. Errors carry no path.
+pub fn parse_schema(contents: &str, format: SchemaFormat) -> Result<Value, SchemaError> {
+    let (location, message) = match format {
+        SchemaFormat::Json => match serde_json::from_str(contents) {
+            Ok(v) => return Ok(v),
+            Err(e) => ((Some(e.line()), Some(e.column())), e.to_string()),
+        },
+        SchemaFormat::Yaml => match serde_yaml::from_str(contents) {
+            Ok(v) => return Ok(v),
+            Err(e) => (e.location().map_or((None, None), |l| (Some(l.line()), Some(l.column()))), e.to_string()),
+        },
+        SchemaFormat::Toml => match toml::from_str(contents) {
+            Ok(v) => return Ok(v),
+            Err(e) => (e.line_col().map_or((None, None), |(l, c)| (Some(l + 1), Some(c + 1))), e.to_string()),
+        },
+    };
+
+    // serde_json reports line 0 for errors not tied to a place in the input
+    let (line, column) = match location {
+        (Some(line), column) if line > 0 => (Some(line), column),
+        _ => (None, None),
+    };
+    Err(SchemaError {
+        path: String::new(),
+        line,
+        column,
+        message: format!("invalid {}: {}", format, strip_location(&message)),
+    })
+}
+
+/// The parsers end their messages with ` at line L column C`, which is reported separately.
+fn strip_location(message: &str) -> &str {
+    match message.rfind(" at line ") {
+        Some(at) if message[at..].contains(" column ") => &message[..at],
+        _ => message,
+    }
+}
+
+#[cfg(test)]
+mod schema_file {
+    use super::*;
+
+    #[test]
+    fn formats_are_detected_by_extension_or_content() {
+        assert_eq!(SchemaFormat::from_path(Path::new("castles.YML")), Some(SchemaFormat::Yaml));
+        assert_eq!(SchemaFormat::from_path(Path::new("castles.toml")), Some(SchemaFormat::Toml));
+        assert_eq!(SchemaFormat::from_path(Path::new("castles")), None);
+
+        assert_eq!(SchemaFormat::detect("\n  {\"root_url\": \"http://x\"}"), SchemaFormat::Json);
+        assert_eq!(SchemaFormat::detect("# castles\nroot_url: http://x"), SchemaFormat::Yaml);
+        assert_eq!(SchemaFormat::detect("---\nroot_url: http://x"), SchemaFormat::Yaml);
+        assert_eq!(SchemaFormat::detect("root_url = \"http://x\""), SchemaFormat::Toml);
+        assert_eq!(SchemaFormat::detect("# castles\n[[endpoints]]\nname = \"a\""), SchemaFormat::Toml);
+    }
+
+    #[test]
+    fn formats_parse_to_the_same_value() {
+        let expected = json!({
+            "root_url": "http://localhost:8000",
+            "endpoints": [
+                { "name": "house", "url": "/houses", "requires": [], "schema": [
+                    { "name": "houseType", "kind": "string", "default_value": "castle" },
+                    { "name": "floors", "kind": "number", "default_value": 3 }
+                ] }
+            ]
+        });
+
+        let yaml = r#"
+# the houses API
+root_url: http://localhost:8000
+endpoints:
+  - name: house
+    url: /houses
+    requires: []
+    schema:
+      - { name: houseType, kind: string, default_value: castle }
+      - name: floors
+        kind: number
+        default_value: 3
+"#;
+        assert_eq!(parse_schema(yaml, SchemaFormat::Yaml), Ok(expected.clone()));
+
+        let toml = r#"
+# the houses API
+root_url = "http://localhost:8000"
+
+[[endpoints]]
+name = "house"
+url = "/houses"
+requires = []
+schema = [
+  { name = "houseType", kind = "string", default_value = "castle" },
+  { name = "floors", kind = "number", default_value = 3 },
+]
+"#;
+        assert_eq!(parse_schema(toml, SchemaFormat::Toml), Ok(expected));
+    }
+
+    #[test]
+    fn errors_point_at_the_source() {
+        let error = parse_schema("{\n  \"root_url\": \"http://x\",\n  \"endpoints\": [,]\n}", SchemaFormat::Json).unwrap_err();
+        assert_eq!((error.line, error.column), (Some(3), Some(17)));
+        assert_eq!(error.message, "invalid JSON: expected value");
+
+        let error = parse_schema("root_url: http://x\nendpoints:\n  - name: a\n   url: /a\n", SchemaFormat::Yaml).unwrap_err();
+        assert_eq!(error.line, Some(4));
+        assert!(error.message.starts_with("invalid YAML: "), "{}", error.message);
+
+        let error = parse_schema("root_url = \"http://x\"\n\n[[endpoints]]\nname = \n", SchemaFormat::Toml).unwrap_err();
+        assert_eq!(error.line, Some(4));
+        assert!(error.message.starts_with("invalid TOML: "), "{}", error.message);
+
+        let error = SchemaError { path: "castles.yaml".to_owned(), ..error };
+        assert!(error.to_string().starts_with("castles.yaml:4:"), "{}", error);
+    }
+
+    #[test]
+    fn included_files_are_merged() {
+        let dir = std::env::temp_dir().join(format!("vaccine-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("common")).unwrap();
+        fs::write(dir.join("common/money.yaml"), "\
+definitions:
+  money: { name: price, kind: float, default_value: 9.5 }
+  audit: { name: createdBy, kind: string, default_value: common }
+endpoints:
+  - { name: currency, url: /currencies, requires: [], schema: [] }
+").unwrap();
+        fs::write(dir.join("houses.json"), r#"{
+            "include": ["common/money.yaml"],
+            "root_url": "http://localhost:8000",
+            "definitions": { "audit": { "name": "createdBy", "kind": "string", "default_value": "houses" } },
+            "endpoints": [{ "name": "house", "url": "/houses", "requires": [], "schema": [] }]
+        }"#).unwrap();
+
+        let v = load_schema(dir.join("houses.json").to_str().unwrap()).unwrap();
+        assert_eq!(v.get("include"), None);
+        assert_eq!(v["root_url"], "http://localhost:8000");
+        assert_eq!(v["endpoints"][0]["name"], "currency");
+        assert_eq!(v["endpoints"][1]["name"], "house");
+        assert_eq!(v["definitions"]["money"]["kind"], "float");
+        assert_eq!(v["definitions"]["audit"]["default_value"], "houses");
+
+        fs::write(dir.join("a.toml"), "include = \"common/b.toml\"\n").unwrap();
+        fs::write(dir.join("common/b.toml"), "include = [\"../a.toml\"]\n").unwrap();
+        let error = load_schema(dir.join("a.toml").to_str().unwrap()).unwrap_err();
+        assert_eq!(error.message, format!("include cycle: {} -> {} -> {}",
+                                          dir.join("a.toml").display(),
+                                          dir.join("common/b.toml").display(),
+                                          dir.join("common/../a.toml").display()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn files_included_twice_are_read_once() {
+        let dir = std::env::temp_dir().join(format!("vaccine-include-twice-{}", std::process::id()));
+        fs::create_dir_all(dir.join("common")).unwrap();
+        fs::write(dir.join("common/money.yaml"), "\
+endpoints:
+  - { name: currency, url: /currencies, requires: [], schema: [] }
+").unwrap();
+        fs::write(dir.join("houses.yaml"), "include: common/money.yaml\nendpoints: []\n").unwrap();
+        fs::write(dir.join("shops.yaml"), "include: ./common/money.yaml\nendpoints: []\n").unwrap();
+        fs::write(dir.join("town.yaml"), "include: [houses.yaml, shops.yaml]\nroot_url: http://localhost:8000\n").unwrap();
+
+        let v = load_schema(dir.join("town.yaml").to_str().unwrap()).unwrap();
+        assert_eq!(v["endpoints"], json!([{ "name": "currency", "url": "/currencies", "requires": [], "schema": [] }]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
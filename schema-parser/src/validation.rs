@@ -0,0 +1,575 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Error, Formatter, Write};
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use serde_json::Value;
+
+use rest_client::AuthType;
+use rest_client::login::Login;
+
+use crate::datetime_parser::{invalid_now, is_now, parse_datetime};
+use crate::definitions::resolve_refs;
+use crate::expectations::StatusPattern;
+use crate::interpolation::interpolate_schema;
+use crate::payload_item::ItemType;
+use crate::profiles::apply_profile;
+use crate::schema_file::load_schema;
+
+/// Something wrong with a schema and where: a file position such as `castles.yaml:12:5`, or a JSON
+/// pointer into the loaded schema such as `castles.yaml#/endpoints/1/schema/0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub location: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(location: &str, message: String) -> Self {
+        Problem { location: location.to_owned(), message }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Every problem with the schema at `path` that would stop `Session::from_file_with_profile` reading it
+/// or the object graph being built from it, or that would be silently ignored, like a second endpoint of
+/// the same name.
+///
+/// A broken `$ref`, unknown profile or unset variable is reported along with the problems found in the
+/// rest of the schema, which is checked as it stands without that step.
+pub fn validate_file(path: &str, profile: Option<&str>) -> Vec<Problem> {
+    let v = match load_schema(path) {
+        Ok(v) => v,
+        Err(e) => {
+            let location = match (e.line, e.column) {
+                (Some(line), Some(column)) => format!("{}:{}:{}", e.path, line, column),
+                (Some(line), None) => format!("{}:{}", e.path, line),
+                _ => e.path,
+            };
+            return vec![Problem::new(&location, e.message)];
+        }
+    };
+
+    let mut problems = Vec::new();
+    let v = resolve_refs(&v).unwrap_or_else(|message| {
+        problems.push(Problem::new(path, message));
+        v
+    });
+    let v = match profile.map(|profile| apply_profile(&v, profile)) {
+        Some(Ok(v)) => v,
+        Some(Err(message)) => {
+            problems.push(Problem::new(path, message));
+            v
+        }
+        None => v,
+    };
+    // Values which could not be expanded are only reported as such, not as bad values in their own right
+    let (v, unresolved) = match interpolate_schema(&v) {
+        Ok(v) => (v, vec![]),
+        Err(e) => (v, e.unresolved),
+    };
+    let skipped: Vec<&str> = unresolved.iter().map(|u| u.location.as_str()).collect();
+
+    problems.extend(unresolved.iter()
+        .map(|u| Problem::new(&format!("{}#{}", path, u.location), format!("${{{}}}: {}", u.reference, u.reason))));
+    problems.extend(validate(&v).into_iter()
+        .filter(|p| !skipped.contains(&p.location.as_str()))
+        .map(|p| Problem { location: format!("{}#{}", path, p.location), ..p }));
+    problems
+}
+
+/// The problems with a loaded schema, located by JSON pointers.
+pub fn validate(v: &Value) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if !v.is_object() {
+        problems.push(Problem::new("", "the schema is not an object".to_owned()));
+        return problems;
+    }
+
+    if !v["root_url"].is_string() {
+        problems.push(Problem::new("/root_url", "missing or not a string".to_owned()));
+    }
+    if let Some(auth_type) = v.get("auth_type") {
+        if let Err(e) = serde_json::from_value::<AuthType>(auth_type.clone()) {
+            problems.push(Problem::new("/auth_type", format!("not a known auth type: {}", e)));
+        }
+    }
+    if let Some(login) = v.get("login") {
+        if let Err(e) = serde_json::from_value::<Login>(login.clone()) {
+            problems.push(Problem::new("/login", format!("needs a url, body and token_pointer: {}", e)));
+        }
+        if v["auth_type"] != "Bearer" && v["auth_type"] != "JWT" {
+            problems.push(Problem::new("/login", "only used with an auth_type of Bearer or JWT".to_owned()));
+        }
+    }
+    check_parameters(v, "", &mut problems);
+    check_expectations(v, "", &mut problems);
+
+    let endpoints = match v["endpoints"].as_array() {
+        Some(endpoints) => endpoints,
+        None => {
+            problems.push(Problem::new("/endpoints", "missing or not an array".to_owned()));
+            return problems;
+        }
+    };
+
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        if let Some(name) = endpoint["name"].as_str() {
+            match names.get(name) {
+                Some(first) => problems.push(Problem::new(
+                    &format!("/endpoints/{}/name", i),
+                    format!("duplicate endpoint name {}, first used at /endpoints/{}", name, first),
+                )),
+                None => { names.insert(name, i); }
+            }
+        }
+    }
+
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        check_endpoint(endpoint, &format!("/endpoints/{}", i), &names, &mut problems);
+    }
+    let mut visited = HashSet::new();
+    for i in 0..endpoints.len() {
+        check_cycles(i, endpoints, &names, &mut vec![], &mut visited, &mut problems);
+    }
+    problems
+}
+
+fn check_endpoint(endpoint: &Value, location: &str, names: &HashMap<&str, usize>, problems: &mut Vec<Problem>) {
+    if !endpoint.is_object() {
+        problems.push(Problem::new(location, "the endpoint is not an object".to_owned()));
+        return;
+    }
+    for field in &["name", "url"] {
+        if !endpoint[field].is_string() {
+            problems.push(Problem::new(&format!("{}/{}", location, field), "missing or not a string".to_owned()));
+        }
+    }
+    if endpoint.get("delete_url").map_or(false, |u| !u.is_string()) {
+        problems.push(Problem::new(&format!("{}/delete_url", location), "not a string".to_owned()));
+    }
+    if endpoint.get("weight").map_or(false, |w| !w.is_u64()) {
+        problems.push(Problem::new(&format!("{}/weight", location), "not a non negative integer".to_owned()));
+    }
+
+    match endpoint["requires"].as_array() {
+        Some(requires) => for (k, dependency) in requires.iter().enumerate() {
+            let location = format!("{}/requires/{}", location, k);
+            match dependency.as_str() {
+                Some(name) if !names.contains_key(name) => {
+                    problems.push(Problem::new(&location, format!("requires unknown endpoint {}", name)));
+                }
+                Some(_) => {}
+                None => problems.push(Problem::new(&location, "not a string".to_owned())),
+            }
+        },
+        None => problems.push(Problem::new(&format!("{}/requires", location), "missing or not an array".to_owned())),
+    }
+
+    check_schema(&endpoint["schema"], &format!("{}/schema", location), true, problems);
+    if let Some(schema) = endpoint.get("response_schema") {
+        check_response_schema(schema, &format!("{}/response_schema", location), problems);
+    }
+    check_parameters(endpoint, location, problems);
+    check_expectations(endpoint, location, problems);
+}
+
+/// Follows `requires` from the endpoint at `i`, reporting each one which leads back to an endpoint
+/// on the current `path`.
+fn check_cycles(
+    i: usize,
+    endpoints: &[Value],
+    names: &HashMap<&str, usize>,
+    path: &mut Vec<usize>,
+    visited: &mut HashSet<usize>,
+    problems: &mut Vec<Problem>,
+) {
+    if !visited.insert(i) {
+        return;
+    }
+    path.push(i);
+    let requires = endpoints[i]["requires"].as_array().into_iter().flatten();
+    for (k, dependency) in requires.enumerate() {
+        let next = match dependency.as_str().and_then(|name| names.get(name)) {
+            Some(next) => *next,
+            None => continue,
+        };
+        match path.iter().position(|p| *p == next) {
+            Some(start) => {
+                let cycle: Vec<&str> = path[start..].iter().chain(Some(&next))
+                    .map(|p| endpoints[*p]["name"].as_str().unwrap_or_default())
+                    .collect();
+                problems.push(Problem::new(
+                    &format!("/endpoints/{}/requires/{}", i, k),
+                    format!("requires cycle {}", cycle.join(" -> ")),
+                ));
+            }
+            None => check_cycles(next, endpoints, names, path, visited, problems),
+        }
+    }
+    path.pop();
+}
+
+/// `expectations`, whose `valid`, `type_confused` and `never` are arrays of statuses.
+fn check_expectations(v: &Value, location: &str, problems: &mut Vec<Problem>) {
+    let location = format!("{}/expectations", location);
+    let expectations = match v.get("expectations") {
+        None => return,
+        Some(Value::Object(expectations)) => expectations,
+        Some(_) => return problems.push(Problem::new(&location, "not an object".to_owned())),
+    };
+    for key in &["valid", "type_confused", "never"] {
+        let location = format!("{}/{}", location, key);
+        match expectations.get(*key) {
+            None => {}
+            Some(Value::Array(statuses)) => for (k, status) in statuses.iter().enumerate() {
+                if let Err(message) = StatusPattern::parse(status) {
+                    problems.push(Problem::new(&format!("{}/{}", location, k), message));
+                }
+            },
+            Some(_) => problems.push(Problem::new(&location, "not an array".to_owned())),
+        }
+    }
+}
+
+/// A `response_schema`, either an array of entries or an object with `fields` and `allow_extra_fields`.
+fn check_response_schema(schema: &Value, location: &str, problems: &mut Vec<Problem>) {
+    if !schema.is_object() {
+        return check_schema(schema, location, false, problems);
+    }
+    if schema.get("allow_extra_fields").is_some_and(|a| !a.is_boolean()) {
+        problems.push(Problem::new(&format!("{}/allow_extra_fields", location), "not true or false".to_owned()));
+    }
+    check_schema(&schema["fields"], &format!("{}/fields", location), false, problems);
+}
+
+/// `headers`, `query` and `cookies`, whose values are strings, numbers, booleans or schema entries.
+fn check_parameters(v: &Value, location: &str, problems: &mut Vec<Problem>) {
+    for kind in &["headers", "query", "cookies"] {
+        let location = format!("{}/{}", location, kind);
+        let parameters = match v.get(kind) {
+            None => continue,
+            Some(Value::Object(parameters)) => parameters,
+            Some(_) => {
+                problems.push(Problem::new(&location, "not an object".to_owned()));
+                continue;
+            }
+        };
+        for (name, value) in parameters {
+            let location = format!("{}/{}", location, name.replace('~', "~0").replace('/', "~1"));
+            match value {
+                Value::String(_) | Value::Number(_) | Value::Bool(_) => {}
+                Value::Object(spec) => {
+                    let mut spec = spec.clone();
+                    spec.insert("name".to_owned(), Value::from(name.as_str()));
+                    check_item(&Value::Object(spec), &location, true, problems);
+                }
+                _ => problems.push(Problem::new(&location, "not a string, number, boolean or schema entry".to_owned())),
+            }
+        }
+    }
+}
+
+/// The entries of a `schema`, or of a `response_schema` when they need no `defaults`.
+fn check_schema(schema: &Value, location: &str, defaults: bool, problems: &mut Vec<Problem>) {
+    let items = match schema.as_array() {
+        Some(items) => items,
+        None => {
+            problems.push(Problem::new(location, "missing or not an array".to_owned()));
+            return;
+        }
+    };
+
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        // Only left in place by a broken `$ref`, which is reported on its own
+        if item.get("$ref").is_some() {
+            continue;
+        }
+        let item_location = format!("{}/{}", location, i);
+        if let Some(name) = item["name"].as_str() {
+            match names.get(name) {
+                Some(first) => problems.push(Problem::new(
+                    &format!("{}/name", item_location),
+                    format!("duplicate field name {}, first used at {}/{}", name, location, first),
+                )),
+                None => { names.insert(name, i); }
+            }
+        }
+        check_item(item, &item_location, defaults, problems);
+    }
+}
+
+fn check_item(item: &Value, location: &str, defaults: bool, problems: &mut Vec<Problem>) {
+    let mut problem = |field: &str, message: String| {
+        let location = if field.is_empty() { location.to_owned() } else { format!("{}/{}", location, field) };
+        problems.push(Problem::new(&location, message));
+    };
+
+    if !item.is_object() {
+        return problem("", "the schema entry is not an object".to_owned());
+    }
+    if !item["name"].is_string() {
+        problem("name", "missing or not a string".to_owned());
+    }
+    let (kind, item_type) = match item["kind"].as_str() {
+        Some(kind) => match ItemType::from_kind(kind) {
+            Some(item_type) => (kind, item_type),
+            None => return problem("kind", format!(
+                "unknown kind {}, expected one of {}", kind, ItemType::KINDS.join(", ")
+            )),
+        },
+        None => return problem("kind", "missing or not a string".to_owned()),
+    };
+
+    let default = &item["default_value"];
+    let expected = match item_type {
+        _ if !defaults => None,
+        ItemType::String | ItemType::Datetime if !default.is_string() => Some("a string"),
+        ItemType::Number if !default.is_i64() => Some("an integer"),
+        ItemType::Float if !default.is_number() => Some("a number"),
+        ItemType::Boolean if !default.is_boolean() => Some("true or false"),
+        _ => None,
+    };
+    if let Some(expected) = expected {
+        let found = if default.is_null() { "missing".to_owned() } else { format!("{}", default) };
+        problem("default_value", format!("expected {} for a {}, found {}", expected, kind, found));
+    }
+    if !defaults && item.get("optional").is_some_and(|o| !o.is_boolean()) {
+        problem("optional", "not true or false".to_owned());
+    }
+
+    match item_type {
+        ItemType::Mapping if defaults => check_schema(&item["schema"], &format!("{}/schema", location), true, problems),
+        ItemType::Mapping => check_response_schema(&item["schema"], &format!("{}/schema", location), problems),
+        ItemType::Datetime => check_datetime(item, location, problems),
+        _ => {}
+    }
+}
+
+/// Formats a sample datetime with the entry's `format` in its `timezone`, and reads its default back.
+fn check_datetime(item: &Value, location: &str, problems: &mut Vec<Problem>) {
+    let timezone = match item["timezone"].as_str() {
+        Some(timezone) => match timezone.parse::<Tz>() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                problems.push(Problem::new(&format!("{}/timezone", location), format!("unknown timezone {}", timezone)));
+                None
+            }
+        },
+        None => {
+            problems.push(Problem::new(&format!("{}/timezone", location), "missing or not a string".to_owned()));
+            None
+        }
+    };
+    let format = match item["format"].as_str() {
+        Some(format) => Some(format),
+        None => {
+            problems.push(Problem::new(&format!("{}/format", location), "missing or not a string".to_owned()));
+            None
+        }
+    };
+
+    let (tz, format) = match (timezone, format) {
+        (Some(tz), Some(format)) => (tz, format),
+        _ => return,
+    };
+    let mut sample = String::new();
+    if write!(sample, "{}", Utc::now().with_timezone(&tz).format(format)).is_err() {
+        problems.push(Problem::new(&format!("{}/format", location), format!("{} is not a valid strftime format", format)));
+        return;
+    }
+
+    if let Some(default) = item["default_value"].as_str() {
+        let message = if is_now(default) {
+            invalid_now(default)
+        } else if parse_datetime(default, format, &tz.name()).is_none() {
+            Some(format!("{} does not match the format {}, e.g. {}", default, format, sample))
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            problems.push(Problem::new(&format!("{}/default_value", location), message));
+        }
+    }
+}
+
+#[cfg(test)]
+mod validation {
+    use super::*;
+
+    fn messages(v: &Value) -> Vec<String> {
+        validate(v).iter().map(Problem::to_string).collect()
+    }
+
+    #[test]
+    fn valid_schemas_have_no_problems() {
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "auth_type": "Bearer",
+            "login": { "url": "/token", "body": {}, "token_pointer": "/access" },
+            "headers": { "X-Api-Version": { "kind": "number", "default_value": 2 } },
+            "endpoints": [
+                { "name": "house", "url": "/houses", "requires": [], "schema": [
+                    { "name": "houseType", "kind": "string", "default_value": "castle" },
+                    { "name": "price", "kind": "float", "default_value": 10 },
+                    { "name": "built", "kind": "datetime", "timezone": "Asia/Kolkata",
+                      "format": "%Y-%m-%d %H:%M:%S", "default_value": "2001-01-01 11:22:33" },
+                    { "name": "sold", "kind": "datetime", "timezone": "UTC", "format": "%Y-%m-%d",
+                      "default_value": "now - 2days" }
+                ] },
+                { "name": "moat", "url": "/moats", "requires": ["house"], "schema": [] }
+            ]
+        });
+        assert_eq!(messages(&v), Vec::<String>::new());
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let v = json!({
+            "auth_type": "Kerberos",
+            "login": { "url": "/token", "body": {}, "token_pointer": "/access" },
+            "endpoints": [
+                { "name": "house", "url": "/houses", "requires": ["kingdom"], "schema": [
+                    { "name": "houseType", "kind": "text", "default_value": "castle" },
+                    { "name": "floors", "kind": "number", "default_value": 2.5 },
+                    { "name": "material", "kind": "mapping", "schema": [
+                        { "name": "walls", "kind": "string", "default_value": "stone" },
+                        { "name": "walls", "kind": "boolean" }
+                    ] },
+                    { "name": "built", "kind": "datetime", "timezone": "Mars/Olympus", "format": "%Y",
+                      "default_value": "2001" },
+                    { "name": "sold", "kind": "datetime", "timezone": "UTC", "format": "%Y-%m-%d",
+                      "default_value": "01/02/2003" },
+                    { "name": "listed", "kind": "datetime", "timezone": "UTC", "format": "%Y-%m-%d %Q",
+                      "default_value": "now" },
+                    { "name": "visited", "kind": "datetime", "timezone": "UTC", "format": "%Y-%m-%d",
+                      "default_value": "now + soon" }
+                ] },
+                { "name": "house", "url": "/houses/v2", "requires": [], "schema": [], "weight": -1 }
+            ]
+        });
+
+        let today = Utc::now().format("%Y-%m-%d");
+        assert_eq!(messages(&v), vec![
+            "/root_url: missing or not a string".to_owned(),
+            "/auth_type: not a known auth type: unknown variant `Kerberos`, expected one of `Basic`, `Bearer`, \
+             `JWT`, `ApiKey`, `OAuth2ClientCredentials`, `Signed`".to_owned(),
+            "/login: only used with an auth_type of Bearer or JWT".to_owned(),
+            "/endpoints/1/name: duplicate endpoint name house, first used at /endpoints/0".to_owned(),
+            "/endpoints/0/requires/0: requires unknown endpoint kingdom".to_owned(),
+            "/endpoints/0/schema/0/kind: unknown kind text, expected one of string, number, float, boolean, \
+             datetime, mapping".to_owned(),
+            "/endpoints/0/schema/1/default_value: expected an integer for a number, found 2.5".to_owned(),
+            "/endpoints/0/schema/2/schema/1/name: duplicate field name walls, first used at \
+             /endpoints/0/schema/2/schema/0".to_owned(),
+            "/endpoints/0/schema/2/schema/1/default_value: expected true or false for a boolean, found missing".to_owned(),
+            "/endpoints/0/schema/3/timezone: unknown timezone Mars/Olympus".to_owned(),
+            format!("/endpoints/0/schema/4/default_value: 01/02/2003 does not match the format %Y-%m-%d, e.g. {}", today),
+            "/endpoints/0/schema/5/format: %Y-%m-%d %Q is not a valid strftime format".to_owned(),
+            "/endpoints/0/schema/6/default_value: invalid duration soon in now + soon: expected number at 0".to_owned(),
+            "/endpoints/1/weight: not a non negative integer".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn expectations_response_schemas_and_cycles_are_checked() {
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "expectations": { "valid": [200, 700], "never": "5xx" },
+            "endpoints": [
+                { "name": "house", "url": "/houses", "requires": ["moat"], "schema": [],
+                  "expectations": { "type_confused": ["4xx", "6xx"] },
+                  "response_schema": { "allow_extra_fields": "yes", "fields": [
+                      { "name": "id", "kind": "number" },
+                      { "name": "nickname", "kind": "string", "optional": 1 },
+                      { "name": "material", "kind": "mapping", "schema": [{ "name": "tonnage", "kind": "weight" }] }
+                  ] } },
+                { "name": "moat", "url": "/moats", "requires": ["bridge"], "schema": [], "response_schema": [
+                    { "name": "depth", "kind": "float" }
+                ] },
+                { "name": "bridge", "url": "/bridges", "requires": ["house"], "schema": [] },
+                { "name": "tower", "url": "/towers", "requires": ["tower"], "schema": [], "expectations": [] }
+            ]
+        });
+
+        assert_eq!(messages(&v), vec![
+            "/expectations/valid/1: invalid status 700, expected 100 to 599".to_owned(),
+            "/expectations/never: not an array".to_owned(),
+            "/endpoints/0/response_schema/allow_extra_fields: not true or false".to_owned(),
+            "/endpoints/0/response_schema/fields/1/optional: not true or false".to_owned(),
+            "/endpoints/0/response_schema/fields/2/schema/0/kind: unknown kind weight, expected one of string, \
+             number, float, boolean, datetime, mapping".to_owned(),
+            "/endpoints/0/expectations/type_confused/1: invalid status class 6xx, expected e.g. 4xx".to_owned(),
+            "/endpoints/3/expectations: not an object".to_owned(),
+            "/endpoints/2/requires/0: requires cycle house -> moat -> bridge -> house".to_owned(),
+            "/endpoints/3/requires/0: requires cycle tower -> tower".to_owned(),
+        ]);
+
+        let v = json!({
+            "root_url": "http://localhost:8000",
+            "auth_type": { "Signed": { "canonical_string": "{method}", "signature_heder": "X-Sig" } },
+            "endpoints": []
+        });
+        assert_eq!(messages(&v), vec![
+            "/auth_type: not a known auth type: unknown field `signature_heder`, expected one of \
+             `canonical_string`, `signature_header`, `timestamp_header`".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn files_are_validated_as_sessions_read_them() {
+        let path = std::env::temp_dir().join(format!("vaccine-validate-{}.yaml", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "\
+root_url: http://localhost:8000
+endpoints:
+  - { name: house, url: /houses, requires: [], schema: [{ $ref: '#/definitions/owner' }] }
+definitions:
+  owner: { name: owner, kind: string, default_value: '${VACCINE_TEST_UNSET_OWNER}' }
+profiles:
+  staging: { root_url: 3 }
+").unwrap();
+
+        assert_eq!(validate_file(path, None), vec![Problem::new(
+            &format!("{}#/endpoints/0/schema/0/default_value", path),
+            "${VACCINE_TEST_UNSET_OWNER}: not set".to_owned(),
+        )]);
+        std::env::set_var("VACCINE_TEST_VALIDATE_OWNER", "jumbo");
+        std::fs::write(path, std::fs::read_to_string(path).unwrap().replace("UNSET_OWNER", "VALIDATE_OWNER")).unwrap();
+        assert_eq!(validate_file(path, None), vec![]);
+        assert_eq!(validate_file(path, Some("staging")), vec![
+            Problem::new(&format!("{}#/root_url", path), "missing or not a string".to_owned()),
+        ]);
+        assert_eq!(validate_file(path, Some("prod")), vec![
+            Problem::new(path, "no profile `prod`, the schema has staging".to_owned()),
+        ]);
+
+        std::fs::write(path, "\
+root_url: ${VACCINE_TEST_UNSET_URL}
+endpoints:
+  - { name: house, url: /houses, requires: [kingdom], schema: [{ $ref: '#/definitions/owner' }] }
+  - { name: house, url: /houses/v2, requires: [], schema: [] }
+").unwrap();
+        assert_eq!(validate_file(path, Some("staging")), vec![
+            Problem::new(path, "$ref #/definitions/owner does not point at anything".to_owned()),
+            Problem::new(path, "the schema has no `profiles`".to_owned()),
+            Problem::new(&format!("{}#/root_url", path), "${VACCINE_TEST_UNSET_URL}: not set".to_owned()),
+            Problem::new(&format!("{}#/endpoints/1/name", path),
+                         "duplicate endpoint name house, first used at /endpoints/0".to_owned()),
+            Problem::new(&format!("{}#/endpoints/0/requires/0", path), "requires unknown endpoint kingdom".to_owned()),
+        ]);
+
+        std::fs::write(path, "root_url: [").unwrap();
+        let problems = validate_file(path, None);
+        assert_eq!(problems[0].location, format!("{}:2:1", path));
+        assert!(problems[0].message.starts_with("invalid YAML: "));
+        std::fs::remove_file(path).unwrap();
+    }
+}
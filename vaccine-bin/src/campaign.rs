@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Limits on a fuzzing run. The run stops as soon as any one of them is reached.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Budget {
+    pub duration: Option<Duration>,
+    pub distinct_failures: Option<usize>,
+    pub requests: Option<usize>,
+}
+
+impl Budget {
+    pub fn is_unbounded(&self) -> bool {
+        *self == Budget::default()
+    }
+
+    /// Describes the limit `progress` has reached, if any.
+    pub fn exhausted_by(&self, progress: &Progress) -> Option<String> {
+        if let Some(duration) = self.duration {
+            if progress.elapsed >= duration {
+                return Some(format!("ran for {}", humantime::format_duration(duration)));
+            }
+        }
+        if let Some(failures) = self.distinct_failures {
+            if progress.distinct_failures >= failures {
+                return Some(format!("found {} distinct failures", progress.distinct_failures));
+            }
+        }
+        if let Some(requests) = self.requests {
+            if progress.requests >= requests {
+                return Some(format!("sent {} requests", progress.requests));
+            }
+        }
+        None
+    }
+}
+
+/// A snapshot of how far a run has got.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    pub elapsed: Duration,
+    pub requests: usize,
+    pub failures: usize,
+    pub distinct_failures: usize,
+    /// Requests which got no response, other than by timing out.
+    pub errors: usize,
+    pub endpoint_requests: BTreeMap<String, usize>,
+}
+
+impl Progress {
+    pub fn requests_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs() as f64 + f64::from(self.elapsed.subsec_millis()) / 1000.0;
+        if seconds > 0.0 { self.requests as f64 / seconds } else { 0.0 }
+    }
+
+    /// One line for printing while the run goes on.
+    pub fn render_line(&self) -> String {
+        let endpoints: Vec<String> = self.endpoint_requests.iter()
+            .map(|(name, count)| format!("{} {}", name, count))
+            .collect();
+        format!("[{}s] {} requests ({:.1}/s), {} failures ({} distinct), {} errors | {}",
+                self.elapsed.as_secs(), self.requests, self.requests_per_second(),
+                self.failures, self.distinct_failures, self.errors, endpoints.join(", "))
+    }
+
+    /// The report printed once the run is over, saying why it stopped.
+    pub fn render_summary(&self, stopped_by: &str) -> String {
+        let mut out = format!("stopped: {}\n", stopped_by);
+        out.push_str(&format!("elapsed: {}\n", humantime::format_duration(Duration::from_secs(self.elapsed.as_secs()))));
+        out.push_str(&format!("requests: {} ({:.1}/s)\n", self.requests, self.requests_per_second()));
+        out.push_str(&format!("failures: {} ({} distinct)\n", self.failures, self.distinct_failures));
+        out.push_str(&format!("errors: {}\n", self.errors));
+        for (name, count) in &self.endpoint_requests {
+            out.push_str(&format!("  {}: {} requests\n", name, count));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod budget {
+    use super::*;
+
+    fn progress(seconds: u64, requests: usize, distinct_failures: usize) -> Progress {
+        Progress {
+            elapsed: Duration::from_secs(seconds),
+            requests,
+            failures: distinct_failures * 2,
+            distinct_failures,
+            errors: 0,
+            endpoint_requests: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn any_limit_exhausts_the_budget() {
+        let budget = Budget {
+            duration: Some(Duration::from_secs(1800)),
+            distinct_failures: Some(50),
+            requests: Some(100_000),
+        };
+        assert_eq!(budget.exhausted_by(&progress(60, 1000, 3)), None);
+        assert_eq!(budget.exhausted_by(&progress(1800, 1000, 3)), Some("ran for 30m".to_owned()));
+        assert_eq!(budget.exhausted_by(&progress(60, 1000, 50)), Some("found 50 distinct failures".to_owned()));
+        assert_eq!(budget.exhausted_by(&progress(60, 100_002, 3)), Some("sent 100002 requests".to_owned()));
+
+        assert!(Budget::default().is_unbounded());
+        assert_eq!(Budget::default().exhausted_by(&progress(1_000_000, 1_000_000, 1000)), None);
+    }
+
+    #[test]
+    fn progress_is_rendered() {
+        let mut p = progress(10, 250, 1);
+        p.endpoint_requests.insert("castle".to_owned(), 200);
+        p.endpoint_requests.insert("moat".to_owned(), 50);
+        p.errors = 3;
+        assert_eq!(p.render_line(), "[10s] 250 requests (25.0/s), 2 failures (1 distinct), 3 errors | castle 200, moat 50");
+        assert_eq!(p.render_summary("sent 250 requests"), "stopped: sent 250 requests\n\
+                                                           elapsed: 10s\n\
+                                                           requests: 250 (25.0/s)\n\
+                                                           failures: 2 (1 distinct)\n\
+                                                           errors: 3\n  \
+                                                           castle: 200 requests\n  \
+                                                           moat: 50 requests\n");
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+
+use schema_parser::configuration::Session;
+
+#[derive(Debug, Default)]
+pub struct EndpointCoverage {
+    pub payloads_sent: usize,
+    pub fields: BTreeMap<String, usize>,
+    pub pairs: BTreeMap<(String, String), usize>,
+    /// How many times each non-2xx status answered each combination of fields.
+    pub failing_combinations: BTreeMap<Vec<String>, BTreeMap<u16, usize>>,
+}
+
+impl EndpointCoverage {
+    fn new(fields: &[String]) -> Self {
+        let mut coverage = EndpointCoverage::default();
+        for field in fields {
+            coverage.fields.insert(field.clone(), 0);
+        }
+        coverage
+    }
+
+    fn record(&mut self, payload: &Value, status: u16) {
+        let keys: Vec<String> = payload.as_object()
+            .map(|m| m.keys().cloned().collect::<BTreeSet<String>>().into_iter().collect())
+            .unwrap_or_default();
+
+        self.payloads_sent += 1;
+        for (i, key) in keys.iter().enumerate() {
+            *self.fields.entry(key.clone()).or_insert(0) += 1;
+            for other in &keys[i + 1..] {
+                *self.pairs.entry((key.clone(), other.clone())).or_insert(0) += 1;
+            }
+        }
+
+        if !(200..300).contains(&status) {
+            *self.failing_combinations.entry(keys).or_default().entry(status).or_insert(0) += 1;
+        }
+    }
+
+    fn pair_count(&self, a: &str, b: &str) -> usize {
+        let key = if a < b { (a.to_owned(), b.to_owned()) } else { (b.to_owned(), a.to_owned()) };
+        self.pairs.get(&key).cloned().unwrap_or(0)
+    }
+
+    fn to_json(&self) -> Value {
+        let pairs: Vec<Value> = self.pairs.iter()
+            .map(|((a, b), count)| json!({ "fields": [a, b], "count": count }))
+            .collect();
+        let failures: Vec<Value> = self.failing_combinations.iter()
+            .map(|(fields, statuses)| json!({ "fields": fields, "statuses": statuses }))
+            .collect();
+
+        json!({
+            "payloads_sent": self.payloads_sent,
+            "fields": self.fields,
+            "pairs": pairs,
+            "failing_combinations": failures,
+        })
+    }
+
+    fn render_table(&self, out: &mut String) {
+        let names: Vec<&String> = self.fields.keys().collect();
+        let labels: Vec<String> = names.iter()
+            .enumerate()
+            .map(|(i, name)| format!("[{}] {}", i, name))
+            .collect();
+        let width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+        out.push_str(&format!("{:width$}", "", width = width));
+        for i in 0..names.len() {
+            out.push_str(&format!(" {:>6}", format!("[{}]", i)));
+        }
+        out.push('\n');
+
+        for (row, label) in names.iter().zip(&labels) {
+            out.push_str(&format!("{:width$}", label, width = width));
+            for column in &names {
+                let count = if row == column { self.fields[*row] } else { self.pair_count(row, column) };
+                out.push_str(&format!(" {:>6}", count));
+            }
+            out.push('\n');
+        }
+
+        for (fields, statuses) in &self.failing_combinations {
+            let counts: Vec<String> = statuses.iter().map(|(status, count)| format!("{} x{}", status, count)).collect();
+            out.push_str(&format!("  non-2xx for [{}]: {}\n", fields.join(", "), counts.join(", ")));
+        }
+    }
+}
+
+/// Counts how often each field and each pair of fields was sent to an endpoint,
+/// and which field combinations were answered with a non-2xx status.
+#[derive(Debug, Default)]
+pub struct CoverageTracker {
+    endpoints: BTreeMap<String, EndpointCoverage>,
+}
+
+impl CoverageTracker {
+    pub fn new(session: &Session) -> Self {
+        let endpoints = session.endpoints.iter()
+            .map(|(name, endpoint)| {
+                let fields: Vec<String> = endpoint.default_payload()
+                    .as_object()
+                    .map(|m| m.keys().cloned().collect())
+                    .unwrap_or_default();
+                (name.clone(), EndpointCoverage::new(&fields))
+            })
+            .collect();
+        CoverageTracker { endpoints }
+    }
+
+    pub fn record(&mut self, endpoint: &str, payload: &Value, status: u16) {
+        self.endpoints.entry(endpoint.to_owned())
+            .or_default()
+            .record(payload, status);
+    }
+
+    pub fn to_json(&self) -> Value {
+        let endpoints: serde_json::Map<String, Value> = self.endpoints.iter()
+            .map(|(name, coverage)| (name.clone(), coverage.to_json()))
+            .collect();
+        Value::from(endpoints)
+    }
+
+    pub fn render_table(&self) -> String {
+        let mut out = String::new();
+        for (name, coverage) in &self.endpoints {
+            out.push_str(&format!("{} ({} payloads)\n", name, coverage.payloads_sent));
+            coverage.render_table(&mut out);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod coverage_tracker {
+    use super::*;
+
+    #[test]
+    fn fields_and_pairs_are_counted() {
+        let mut tracker = CoverageTracker::default();
+        tracker.record("house", &json!({"a": 1, "b": 2}), 200);
+        tracker.record("house", &json!({"b": 2, "c": 3, "a": 1}), 200);
+
+        let coverage = &tracker.endpoints["house"];
+        assert_eq!(coverage.payloads_sent, 2);
+        assert_eq!(coverage.fields["a"], 2);
+        assert_eq!(coverage.fields["c"], 1);
+        assert_eq!(coverage.pair_count("a", "b"), 2);
+        assert_eq!(coverage.pair_count("c", "a"), 1);
+        assert!(coverage.failing_combinations.is_empty());
+    }
+
+    #[test]
+    fn non_success_combinations_are_recorded() {
+        let mut tracker = CoverageTracker::default();
+        tracker.record("house", &json!({"b": 1, "a": 2}), 500);
+        tracker.record("house", &json!({"a": 2, "b": 1}), 404);
+        tracker.record("house", &json!({"a": 3, "b": 4}), 500);
+
+        let coverage = &tracker.endpoints["house"];
+        let key = vec!["a".to_owned(), "b".to_owned()];
+        assert_eq!(coverage.failing_combinations[&key], vec![(404, 1), (500, 2)].into_iter().collect());
+
+        let report = tracker.to_json();
+        assert_eq!(report["house"]["failing_combinations"][0]["statuses"], json!({ "404": 1, "500": 2 }));
+        assert_eq!(report["house"]["pairs"][0]["fields"], json!(["a", "b"]));
+        assert!(tracker.render_table().contains("non-2xx for [a, b]: 404 x1, 500 x2"));
+    }
+}
@@ -1,6 +1,13 @@
-use schema_parser::configuration::Session;
-use rest_client::AuthType;
+use std::collections::HashMap;
 use std::env;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use rest_client::AuthType;
+use schema_parser::configuration::Session;
 
 pub enum Credentials {
     Token(String),
@@ -10,29 +17,376 @@ pub enum Credentials {
     },
 }
 
-pub fn resolve_credentials(session: &Session) -> Option<Credentials> {
-    let auth_type = session.auth_type;
-    if let Some(auth_type) = auth_type {
-        let c = match auth_type {
-            AuthType::Basic => {
-                let username_password = env::var("VACCINE_CREDENTIALS")
-                    .expect("could not find vaccine token in env!");
-                let (username, password) = username_password.split_at(
-                    username_password.find(':').expect("Invalid username:password - no seperator ':'")
-                );
-                Credentials::UsernamePassword {
-                    username: username.to_owned(),
-                    password: password.to_owned(),
-                }
-            },
-            AuthType::Bearer | AuthType::JWT => {
-                let token = env::var("VACCINE_TOKEN")
-                    .expect("could not find vaccine token in env!");
-                Credentials::Token(token)
-            },
+/// Never prints the secrets themselves.
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Credentials::Token(_) => f.write_str("Token(..)"),
+            Credentials::UsernamePassword { username, .. } => f.debug_struct("UsernamePassword")
+                .field("username", username)
+                .finish(),
+        }
+    }
+}
+
+/// Why credentials could not be had. Messages name the credential and where it was looked for,
+/// never its value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CredentialError {
+    Missing { key: String, searched: Vec<String> },
+    Invalid(String),
+    Source(String),
+}
+
+impl Display for CredentialError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CredentialError::Missing { key, searched } => {
+                write!(f, "no {} found in {}", key, searched.join(", "))
+            }
+            CredentialError::Invalid(m) => write!(f, "invalid credentials: {}", m),
+            CredentialError::Source(m) => write!(f, "failed to read credentials: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for CredentialError {}
+
+/// Somewhere credentials can be looked up by key: `credentials` (as `username:password`), `token`,
+/// `api_key`, `client_id`, `client_secret` or `signing_secret`.
+pub trait CredentialProvider {
+    /// Where the provider looks for `key`, for error messages.
+    fn describe(&self, key: &str) -> String;
+
+    /// The value for `key`, or `None` if this provider does not have one.
+    fn get(&self, key: &str) -> Result<Option<String>, CredentialError>;
+}
+
+/// Reads `key` from the environment variable `<prefix><KEY>`, e.g. `VACCINE_TOKEN`.
+pub struct EnvProvider {
+    prefix: String,
+}
+
+impl EnvProvider {
+    pub fn new(prefix: &str) -> Self {
+        EnvProvider { prefix: prefix.to_owned() }
+    }
+
+    fn variable(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key.to_uppercase())
+    }
+}
+
+impl Default for EnvProvider {
+    fn default() -> Self {
+        EnvProvider::new("VACCINE_")
+    }
+}
+
+impl CredentialProvider for EnvProvider {
+    fn describe(&self, key: &str) -> String {
+        format!("the environment ({})", self.variable(key))
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, CredentialError> {
+        match env::var(self.variable(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(env::VarError::NotUnicode(_)) => {
+                Err(CredentialError::Invalid(format!("{} is not valid unicode", self.variable(key))))
+            }
+        }
+    }
+}
+
+/// Reads one `[profile]` section of an INI style credentials file:
+///
+/// ```ini
+/// [staging]
+/// credentials = jumbo:s3cret
+/// api_key = abc123
+/// ```
+pub struct FileProvider {
+    path: PathBuf,
+    profile: String,
+    values: HashMap<String, String>,
+}
+
+impl FileProvider {
+    pub fn new(path: &Path, profile: &str) -> Result<Self, CredentialError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| CredentialError::Source(format!("{}: {}", path.display(), e)))?;
+        let mut profiles = parse_profiles(&contents)
+            .map_err(|m| CredentialError::Invalid(format!("{}: {}", path.display(), m)))?;
+        let values = profiles.remove(profile).ok_or_else(|| CredentialError::Invalid(
+            format!("no profile [{}] in {}", profile, path.display())
+        ))?;
+        Ok(FileProvider { path: path.to_path_buf(), profile: profile.to_owned(), values })
+    }
+}
+
+impl CredentialProvider for FileProvider {
+    fn describe(&self, _key: &str) -> String {
+        format!("{} [{}]", self.path.display(), self.profile)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, CredentialError> {
+        Ok(self.values.get(key).cloned())
+    }
+}
+
+/// Profiles by name, each mapping keys to values. Blank lines and those starting with `#` or `;` are skipped.
+fn parse_profiles(contents: &str) -> Result<HashMap<String, HashMap<String, String>>, String> {
+    let mut profiles: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = line[1..line.len() - 1].trim().to_owned();
+            profiles.entry(name.clone()).or_default();
+            current = Some(name);
+            continue;
+        }
+
+        // The line is not echoed in errors, it may hold a secret
+        let separator = line.find('=').ok_or_else(|| format!("line {} is not `key = value`", number + 1))?;
+        let profile = current.as_ref().ok_or_else(|| format!("line {} is outside of a [profile]", number + 1))?;
+        profiles.get_mut(profile).unwrap().insert(
+            line[..separator].trim().to_owned(),
+            line[separator + 1..].trim().to_owned(),
+        );
+    }
+    Ok(profiles)
+}
+
+/// Runs an external helper, like a git credential helper, as `<command> <key>` through the shell.
+/// Its standard output, without the trailing newline, is the value; no output means it has none.
+pub struct CommandProvider {
+    command: String,
+}
+
+impl CommandProvider {
+    pub fn new(command: &str) -> Self {
+        CommandProvider { command: command.to_owned() }
+    }
+}
+
+impl CredentialProvider for CommandProvider {
+    fn describe(&self, _key: &str) -> String {
+        format!("the output of `{}`", self.command)
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, CredentialError> {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$0\"", self.command))
+            .arg(key)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|e| CredentialError::Source(format!("could not run `{}`: {}", self.command, e)))?;
+
+        if !output.status.success() {
+            return Err(CredentialError::Source(format!("`{}` {} for {}", self.command, output.status, key)));
+        }
+        let value = String::from_utf8(output.stdout)
+            .map_err(|_| CredentialError::Invalid(format!("`{}` printed invalid unicode for {}", self.command, key)))?;
+        let value = value.trim_end_matches(|c| c == '\n' || c == '\r');
+        Ok(if value.is_empty() { None } else { Some(value.to_owned()) })
+    }
+}
+
+/// Reads values from standard input, prompting on standard error with echo turned off
+/// when the input is a terminal.
+pub struct PromptProvider;
+
+impl PromptProvider {
+    fn read_line(&self, prompt: &str, secret: bool) -> Result<String, CredentialError> {
+        let interactive = atty::is(atty::Stream::Stdin);
+        if interactive {
+            eprint!("{}: ", prompt);
+            let _ = io::stderr().flush();
+        }
+        let hide = interactive && secret && set_echo(false);
+
+        let mut line = String::new();
+        let read = io::stdin().lock().read_line(&mut line);
+        if hide {
+            set_echo(true);
+            eprintln!();
+        }
+        match read {
+            Ok(0) => Err(CredentialError::Source(format!("standard input closed before the {}", prompt))),
+            Ok(_) => Ok(line.trim_end_matches(|c| c == '\n' || c == '\r').to_owned()),
+            Err(e) => Err(CredentialError::Source(format!("standard input: {}", e))),
+        }
+    }
+}
+
+fn set_echo(on: bool) -> bool {
+    Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .status()
+        .map_or(false, |s| s.success())
+}
+
+impl CredentialProvider for PromptProvider {
+    fn describe(&self, _key: &str) -> String {
+        "standard input".to_owned()
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, CredentialError> {
+        let value = if key == "credentials" {
+            let username = self.read_line("username", false)?;
+            let password = self.read_line("password", true)?;
+            format!("{}:{}", username, password)
+        } else {
+            self.read_line(&key.replace('_', " "), true)?
         };
-        Some(c)
-    } else {
-        None
+        Ok(Some(value))
+    }
+}
+
+/// The first value for `key` from `providers`, asked in order.
+fn lookup(providers: &[Box<dyn CredentialProvider>], key: &str) -> Result<String, CredentialError> {
+    for provider in providers {
+        if let Some(value) = provider.get(key)? {
+            return Ok(value);
+        }
+    }
+    Err(CredentialError::Missing {
+        key: key.to_owned(),
+        searched: providers.iter().map(|p| p.describe(key)).collect(),
+    })
+}
+
+/// Splits `username:password` at the first `:`, so that passwords may contain one.
+fn username_password(value: &str) -> Result<Credentials, CredentialError> {
+    let separator = value.find(':').ok_or_else(|| {
+        CredentialError::Invalid("credentials must be `username:password`".to_owned())
+    })?;
+    Ok(Credentials::UsernamePassword {
+        username: value[..separator].to_owned(),
+        password: value[separator + 1..].to_owned(),
+    })
+}
+
+pub fn resolve_credentials(session: &Session, providers: &[Box<dyn CredentialProvider>])
+                           -> Result<Option<Credentials>, CredentialError> {
+    if session.login.is_some() {
+        return username_password(&lookup(providers, "credentials")?).map(Some);
+    }
+
+    let auth_type = match &session.auth_type {
+        Some(auth_type) => auth_type,
+        None => return Ok(None),
+    };
+    let c = match auth_type {
+        AuthType::Basic => username_password(&lookup(providers, "credentials")?)?,
+        AuthType::Bearer | AuthType::JWT => Credentials::Token(lookup(providers, "token")?),
+        AuthType::OAuth2ClientCredentials { .. } => Credentials::UsernamePassword {
+            username: lookup(providers, "client_id")?,
+            password: lookup(providers, "client_secret")?,
+        },
+        AuthType::Signed { .. } => Credentials::Token(lookup(providers, "signing_secret")?),
+        AuthType::ApiKey { .. } => Credentials::Token(lookup(providers, "api_key")?),
+    };
+    Ok(Some(c))
+}
+
+#[cfg(test)]
+mod credentials {
+    use super::*;
+
+    struct Fixed(Vec<(&'static str, &'static str)>);
+
+    impl CredentialProvider for Fixed {
+        fn describe(&self, _key: &str) -> String {
+            "fixed".to_owned()
+        }
+
+        fn get(&self, key: &str) -> Result<Option<String>, CredentialError> {
+            Ok(self.0.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string()))
+        }
+    }
+
+    fn session(auth_type: &str) -> Session {
+        Session::new(&json!({ "root_url": "http://localhost", "auth_type": auth_type, "endpoints": [] }))
+    }
+
+    #[test]
+    fn passwords_keep_everything_after_the_first_colon() {
+        match username_password("jumbo:p:ss").unwrap() {
+            Credentials::UsernamePassword { username, password } => {
+                assert_eq!(username, "jumbo");
+                assert_eq!(password, "p:ss");
+            }
+            other => panic!("expected a username and password, got {:?}", other),
+        }
+
+        let error = username_password("s3cret").unwrap_err();
+        assert_eq!(error.to_string(), "invalid credentials: credentials must be `username:password`");
+    }
+
+    #[test]
+    fn providers_are_asked_in_order() {
+        let providers: Vec<Box<dyn CredentialProvider>> = vec![
+            Box::new(Fixed(vec![("token", "first")])),
+            Box::new(Fixed(vec![("token", "second"), ("credentials", "jumbo:s3cret")])),
+        ];
+        let token = resolve_credentials(&session("Bearer"), &providers).unwrap().unwrap();
+        assert_eq!(format!("{:?}", token), "Token(..)");
+        match token {
+            Credentials::Token(token) => assert_eq!(token, "first"),
+            other => panic!("expected a token, got {:?}", other),
+        }
+
+        let basic = resolve_credentials(&session("Basic"), &providers).unwrap().unwrap();
+        assert_eq!(format!("{:?}", basic), r#"UsernamePassword { username: "jumbo" }"#);
+
+        let providers: Vec<Box<dyn CredentialProvider>> = vec![Box::new(EnvProvider::new("VACCINE_TEST_UNSET_"))];
+        let error = resolve_credentials(&session("JWT"), &providers).unwrap_err();
+        assert_eq!(error.to_string(), "no token found in the environment (VACCINE_TEST_UNSET_TOKEN)");
+        assert!(resolve_credentials(&Session::new(&json!({ "root_url": "/", "endpoints": [] })), &providers)
+            .unwrap().is_none());
+    }
+
+    #[test]
+    fn credentials_files_have_profiles() {
+        let path = env::temp_dir().join(format!("vaccine-credentials-{}", std::process::id()));
+        fs::write(&path, "# local development\n\
+                          [default]\n\
+                          credentials = jumbo:s3cret\n\
+                          \n\
+                          [staging]\n\
+                          ; from the vault\n\
+                          api_key = abc=123\n").unwrap();
+
+        let staging = FileProvider::new(&path, "staging").unwrap();
+        assert_eq!(staging.get("api_key"), Ok(Some("abc=123".to_owned())));
+        assert_eq!(staging.get("credentials"), Ok(None));
+        assert_eq!(FileProvider::new(&path, "default").unwrap().get("credentials"), Ok(Some("jumbo:s3cret".to_owned())));
+
+        let error = FileProvider::new(&path, "prod").err().unwrap();
+        assert_eq!(error, CredentialError::Invalid(format!("no profile [prod] in {}", path.display())));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(parse_profiles("token = s3cret"), Err("line 1 is outside of a [profile]".to_owned()));
+        assert_eq!(parse_profiles("[a]\ns3cret"), Err("line 2 is not `key = value`".to_owned()));
+    }
+
+    #[test]
+    fn commands_print_the_value() {
+        let helper = CommandProvider::new("f() { [ \"$1\" = token ] && echo s3cret; true; }; f");
+        assert_eq!(helper.get("token"), Ok(Some("s3cret".to_owned())));
+        assert_eq!(helper.get("api_key"), Ok(None));
+
+        let error = CommandProvider::new("exit 3").get("token").unwrap_err();
+        assert_eq!(error.to_string(), "failed to read credentials: `exit 3` exit status: 3 for token");
     }
 }
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use serde_json::Value;
+
+use rest_client::request_options::RequestOptions;
+use rest_client::response::{RestClientError, RestResponse};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FindingKind {
+    ServerError,
+    ReadAfterWriteMismatch,
+    UnexpectedStatus,
+    ResponseSchemaViolation,
+    Timeout,
+}
+
+impl FindingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FindingKind::ServerError => "server_error",
+            FindingKind::ReadAfterWriteMismatch => "read_after_write_mismatch",
+            FindingKind::UnexpectedStatus => "unexpected_status",
+            FindingKind::ResponseSchemaViolation => "response_schema_violation",
+            FindingKind::Timeout => "timeout",
+        }
+    }
+}
+
+/// A single request sent while fuzzing, along with what came back.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub method: String,
+    pub url: String,
+    pub payload: Option<Value>,
+    pub status: Option<u16>,
+    pub body: String,
+    pub error: Option<RestClientError>,
+    /// The headers, query parameters and cookies sent on top of the client's own.
+    pub options: RequestOptions,
+}
+
+impl Step {
+    pub fn new(method: &str, url: &str, payload: Option<Value>, result: Result<RestResponse, RestClientError>) -> Self {
+        let (status, body, error) = match result {
+            Ok(response) => (Some(response.status), response.text(), None),
+            Err(e) => (None, String::new(), Some(e)),
+        };
+        Step { method: method.to_owned(), url: url.to_owned(), payload, status, body, error, options: RequestOptions::default() }
+    }
+
+    pub fn with_options(mut self, options: RequestOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "method": self.method,
+            "url": self.url,
+            "payload": self.payload,
+            "status": self.status,
+            "body": self.body,
+            "error": self.error.as_ref().map(|e| e.to_string()),
+            "options": self.options.to_json(),
+        })
+    }
+}
+
+/// A failure along with every step leading up to it, in the order they were sent,
+/// so that the sequence can be replayed.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub endpoint: String,
+    pub message: String,
+    pub steps: Vec<Step>,
+}
+
+impl Finding {
+    pub fn to_json(&self) -> Value {
+        let steps: Vec<Value> = self.steps.iter().map(Step::to_json).collect();
+        json!({
+            "kind": self.kind.as_str(),
+            "endpoint": self.endpoint,
+            "message": self.message,
+            "steps": steps,
+        })
+    }
+}
+
+/// The findings of a run, and a tally of the requests which got no response at all.
+#[derive(Debug, Default)]
+pub struct FindingStore {
+    findings: Vec<Finding>,
+    distinct: BTreeMap<(&'static str, String, String), usize>,
+    errors: BTreeMap<(String, String), usize>,
+}
+
+impl FindingStore {
+    pub fn record(&mut self, finding: Finding) {
+        let key = (finding.kind.as_str(), finding.endpoint.clone(), finding.message.clone());
+        *self.distinct.entry(key).or_insert(0) += 1;
+        self.findings.push(finding);
+    }
+
+    /// Counts a request to `endpoint` which failed without a response, other than by timing out.
+    pub fn record_error(&mut self, endpoint: &str, error: &RestClientError) {
+        *self.errors.entry((endpoint.to_owned(), error.to_string())).or_insert(0) += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+
+    /// Number of findings which differ in kind, endpoint or message.
+    pub fn distinct_len(&self) -> usize {
+        self.distinct.len()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.errors.values().sum()
+    }
+
+    /// Each distinct finding and error once, with how often it happened.
+    pub fn render_summary(&self) -> String {
+        let mut out = String::new();
+        if !self.distinct.is_empty() {
+            out.push_str("findings:\n");
+        }
+        for ((kind, endpoint, message), count) in &self.distinct {
+            out.push_str(&format!("  [{}] {}: {} (x{})\n", kind, endpoint, message, count));
+        }
+        if !self.errors.is_empty() {
+            out.push_str("errors:\n");
+        }
+        for ((endpoint, message), count) in &self.errors {
+            out.push_str(&format!("  {}: {} (x{})\n", endpoint, message, count));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::from(self.findings.iter().map(Finding::to_json).collect::<Vec<Value>>())
+    }
+
+    pub fn write_to(&self, path: &str) {
+        let mut file = File::create(path).expect(&format!("failed to create {}", path));
+        let report = serde_json::to_string_pretty(&self.to_json()).unwrap();
+        file.write_all(report.as_bytes()).expect(&format!("failed to write {}", path));
+    }
+}
+
+#[cfg(test)]
+mod finding_store {
+    use super::*;
+
+    #[test]
+    fn findings_keep_their_steps_in_order() {
+        let mut store = FindingStore::default();
+        store.record(Finding {
+            kind: FindingKind::ServerError,
+            endpoint: "house".to_owned(),
+            message: "500 on second patch".to_owned(),
+            steps: vec![
+                Step { method: "PATCH".to_owned(), url: "/house/1".to_owned(), payload: Some(json!({"a": 1})), status: Some(200), body: "".to_owned(), error: None, options: RequestOptions::default() },
+                Step { method: "GET".to_owned(), url: "/house/1".to_owned(), payload: None, status: Some(200), body: "{}".to_owned(), error: None, options: RequestOptions::default() },
+                Step { method: "PATCH".to_owned(), url: "/house/1".to_owned(), payload: Some(json!({"a": 2})), status: Some(500), body: "oops".to_owned(), error: None, options: RequestOptions::default() },
+            ],
+        });
+
+        assert_eq!(store.len(), 1);
+        let report = store.to_json();
+        assert_eq!(report[0]["kind"], "server_error");
+        assert_eq!(report[0]["steps"][1]["method"], "GET");
+        assert_eq!(report[0]["steps"][2]["payload"], json!({"a": 2}));
+        assert_eq!(report[0]["steps"][2]["status"], 500);
+    }
+
+    #[test]
+    fn repeated_findings_are_not_distinct() {
+        let finding = |message: &str| Finding {
+            kind: FindingKind::ServerError,
+            endpoint: "house".to_owned(),
+            message: message.to_owned(),
+            steps: vec![],
+        };
+        let mut store = FindingStore::default();
+        store.record(finding("PATCH /house/1 returned 500"));
+        store.record(finding("PATCH /house/1 returned 500"));
+        store.record(finding("PATCH /house/1 returned 503"));
+
+        assert_eq!(store.len(), 3);
+        assert_eq!(store.distinct_len(), 2);
+
+        store.record_error("house", &RestClientError::Connect("connection refused".to_owned()));
+        store.record_error("house", &RestClientError::Connect("connection refused".to_owned()));
+        assert_eq!(store.error_count(), 2);
+        assert_eq!(store.render_summary(), "findings:\n  \
+                                            [server_error] house: PATCH /house/1 returned 500 (x2)\n  \
+                                            [server_error] house: PATCH /house/1 returned 503 (x1)\n\
+                                            errors:\n  \
+                                            house: connection failed: connection refused (x2)\n");
+    }
+}
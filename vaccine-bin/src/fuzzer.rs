@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crossbeam_utils::thread::scope;
+use rand::Rng;
+use serde_json::Value;
+
+use rest_client::{Method, RestClient};
+use rest_client::request_options::RequestOptions;
+use rest_client::response::RestClientError;
+use schema_parser::configuration::Session;
+use schema_parser::dependency_resolver::Creation;
+use schema_parser::expectations::MutationClass;
+use schema_parser::rest_endpoint::Endpoint;
+
+use crate::campaign::{Budget, Progress};
+use crate::coverage::CoverageTracker;
+use crate::findings::{Finding, FindingKind, FindingStore, Step};
+
+/// Sends randomized patch sequences to the objects of a session from a pool of worker threads.
+/// Sequences on the same object never overlap, so the most workers doing useful work at once is
+/// the number of endpoints.
+pub struct Fuzzer<'a> {
+    session: &'a Session,
+    client: &'a RestClient,
+    sequence_length: usize,
+    verify_writes: bool,
+    type_confusion_rate: f64,
+    workers: usize,
+    stop: Arc<AtomicBool>,
+    stopped_by: Mutex<Option<String>>,
+    budget: Budget,
+    progress_interval: Option<Duration>,
+    object_locks: HashMap<String, Mutex<()>>,
+    requests: AtomicUsize,
+    endpoint_requests: HashMap<String, AtomicUsize>,
+    coverage: Mutex<CoverageTracker>,
+    findings: Mutex<FindingStore>,
+}
+
+impl<'a> Fuzzer<'a> {
+    pub fn new(session: &'a Session, client: &'a RestClient) -> Self {
+        Fuzzer {
+            session,
+            client,
+            sequence_length: 1,
+            verify_writes: false,
+            type_confusion_rate: 0.0,
+            workers: 1,
+            stop: Arc::new(AtomicBool::new(false)),
+            stopped_by: Mutex::new(None),
+            budget: Budget::default(),
+            progress_interval: None,
+            object_locks: session.endpoints.keys().map(|name| (name.clone(), Mutex::new(()))).collect(),
+            requests: AtomicUsize::new(0),
+            endpoint_requests: session.endpoints.keys().map(|name| (name.clone(), AtomicUsize::new(0))).collect(),
+            coverage: Mutex::new(CoverageTracker::new(session)),
+            findings: Mutex::new(FindingStore::default()),
+        }
+    }
+
+    /// Number of successive patches applied to the same object before moving on.
+    /// When more than one, the object is fetched between patches.
+    pub fn sequence_length(mut self, sequence_length: usize) -> Self {
+        self.sequence_length = sequence_length.max(1);
+        self
+    }
+
+    /// Fetches the object after every successful patch and checks that it holds what was sent.
+    pub fn verify_writes(mut self, verify_writes: bool) -> Self {
+        self.verify_writes = verify_writes;
+        self
+    }
+
+    /// Fraction of patches sent with a field of the wrong type instead of a valid payload.
+    pub fn type_confusion_rate(mut self, rate: f64) -> Self {
+        self.type_confusion_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Number of threads sending sequences at the same time.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Workers finish their current sequence and stop once `stop` is set.
+    pub fn stop_flag(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Sends `iterations` sequences to every created object, times the weight of its endpoint,
+    /// or keeps going until the budget or the stop flag ends the run when `iterations` is `None`.
+    /// Returns where the run got to and why it stopped.
+    pub fn run(&self, iterations: Option<usize>) -> (Progress, String) {
+        let started = Instant::now();
+        let round = self.round();
+        let limit = iterations.map(|n| n * round.len());
+        let next = AtomicUsize::new(0);
+        let finished = AtomicBool::new(false);
+
+        scope(|scope| {
+            let workers: Vec<_> = (0..self.workers)
+                .map(|_| scope.spawn(|_| {
+                    while !self.stop.load(Ordering::SeqCst) && !round.is_empty() {
+                        if let Some(reason) = self.budget.exhausted_by(&self.progress(started)) {
+                            self.halt(reason);
+                            break;
+                        }
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if limit.map_or(false, |limit| i >= limit) {
+                            break;
+                        }
+                        let name = round[i % round.len()];
+                        let _object = self.object_locks[name].lock().unwrap();
+                        self.run_sequence(&self.session.endpoints[name], &self.session.created[name]);
+                    }
+                }))
+                .collect();
+
+            if let Some(interval) = self.progress_interval {
+                let finished = &finished;
+                scope.spawn(move |_| {
+                    let mut reported = Instant::now();
+                    while !finished.load(Ordering::SeqCst) {
+                        std::thread::sleep(Duration::from_millis(100));
+                        if reported.elapsed() >= interval {
+                            eprintln!("{}", self.progress(started).render_line());
+                            reported = Instant::now();
+                        }
+                    }
+                });
+            }
+
+            let results: Vec<_> = workers.into_iter().map(|worker| worker.join()).collect();
+            finished.store(true, Ordering::SeqCst);
+            if results.iter().any(Result::is_err) {
+                panic!("a fuzzing worker panicked");
+            }
+        }).expect("a fuzzing worker panicked");
+
+        let stopped_by = match self.stopped_by.lock().unwrap().clone() {
+            Some(reason) => reason,
+            None if self.stop.load(Ordering::SeqCst) => "interrupted".to_owned(),
+            None => format!("finished {} iterations", iterations.unwrap_or(0)),
+        };
+        (self.progress(started), stopped_by)
+    }
+
+    /// One round of the endpoints to send sequences to, each repeated by its weight.
+    fn round(&self) -> Vec<&'a String> {
+        let session = self.session;
+        let mut names: Vec<&String> = session.endpoints.keys()
+            .filter(|name| session.created.contains_key(*name))
+            .collect();
+        names.sort();
+
+        let mut round = vec![];
+        for name in names {
+            for _ in 0..session.endpoints[name].weight {
+                round.push(name);
+            }
+        }
+        round
+    }
+
+    /// Stops the workers, remembering the first reason given.
+    fn halt(&self, reason: String) {
+        let mut stopped_by = self.stopped_by.lock().unwrap();
+        if stopped_by.is_none() {
+            *stopped_by = Some(reason);
+        }
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    pub fn progress(&self, started: Instant) -> Progress {
+        let findings = self.findings.lock().unwrap();
+        Progress {
+            elapsed: started.elapsed(),
+            requests: self.requests.load(Ordering::SeqCst),
+            failures: findings.len(),
+            distinct_failures: findings.distinct_len(),
+            errors: findings.error_count(),
+            endpoint_requests: self.endpoint_requests.iter()
+                .map(|(name, count)| (name.clone(), count.load(Ordering::SeqCst)))
+                .collect(),
+        }
+    }
+
+    /// Limits on how long the run goes on, checked before every sequence.
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Prints a progress line to stderr this often while running.
+    pub fn progress_interval(mut self, interval: Option<Duration>) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Hands back the coverage and findings gathered by the run.
+    pub fn finish(self) -> (CoverageTracker, FindingStore) {
+        (self.coverage.into_inner().unwrap(), self.findings.into_inner().unwrap())
+    }
+
+    /// Validates the responses to the requests which built the object graph.
+    pub fn check_creations(&self, creations: &[Creation]) {
+        for creation in creations {
+            let endpoint = &self.session.endpoints[&creation.endpoint];
+            let step = Step {
+                method: "POST".to_owned(),
+                url: creation.url.clone(),
+                payload: Some(creation.payload.clone()),
+                status: Some(creation.status),
+                body: creation.body.to_string(),
+                error: None,
+                options: endpoint.parameters.recorded(&endpoint.parameters.default_options()),
+            };
+            self.check_response_schema(endpoint, &[step]);
+        }
+    }
+
+    fn run_sequence(&self, endpoint: &Endpoint, created: &Value) {
+        let url = endpoint.object_url(created);
+        let mut steps = Vec::new();
+
+        for i in 0..self.sequence_length {
+            let class = if rand::thread_rng().gen_bool(self.type_confusion_rate) {
+                MutationClass::TypeConfused
+            } else {
+                MutationClass::Valid
+            };
+
+            let step = self.patch(endpoint, &url, endpoint.payload_for(class));
+            if let Some(status) = step.status {
+                self.coverage.lock().unwrap().record(&endpoint.name, step.payload.as_ref().unwrap(), status);
+            }
+            let verify = self.verify_writes && class == MutationClass::Valid && is_success(step.status);
+            steps.push(step);
+            if self.check_step(endpoint, &steps)
+                || self.check_expectations(endpoint, class, &steps)
+                || self.check_response_schema(endpoint, &steps) {
+                return;
+            }
+
+            if verify || i + 1 < self.sequence_length {
+                steps.push(self.get(endpoint, &url));
+                if self.check_step(endpoint, &steps) || self.check_response_schema(endpoint, &steps) {
+                    return;
+                }
+                if verify && self.check_read_after_write(endpoint, &steps) {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Checks the status of the last patch against the endpoint's expectations for its payload class.
+    fn check_expectations(&self, endpoint: &Endpoint, class: MutationClass, steps: &[Step]) -> bool {
+        let last = steps.last().unwrap();
+        let message = match endpoint.expectations.check(class, last.status.unwrap()) {
+            Ok(()) => return false,
+            Err(message) => format!("{} {} returned {}", last.method, last.url, message),
+        };
+
+        self.findings.lock().unwrap().record(Finding {
+            kind: FindingKind::UnexpectedStatus,
+            endpoint: endpoint.name.clone(),
+            message,
+            steps: steps.to_vec(),
+        });
+        true
+    }
+
+    /// Validates the body of the last step if it succeeded and the endpoint declares a response schema.
+    fn check_response_schema(&self, endpoint: &Endpoint, steps: &[Step]) -> bool {
+        let schema = match &endpoint.response_schema {
+            Some(schema) => schema,
+            None => return false,
+        };
+        let last = steps.last().unwrap();
+        if !is_success(last.status) || last.body.is_empty() {
+            return false;
+        }
+
+        let problems = match serde_json::from_str::<Value>(&last.body) {
+            Ok(body) => schema.validate(&body),
+            Err(_) => vec!["body is not JSON".to_owned()],
+        };
+        if problems.is_empty() {
+            return false;
+        }
+
+        self.findings.lock().unwrap().record(Finding {
+            kind: FindingKind::ResponseSchemaViolation,
+            endpoint: endpoint.name.clone(),
+            message: format!("{} {} response does not match schema: {}", last.method, last.url, problems.join("; ")),
+            steps: steps.to_vec(),
+        });
+        true
+    }
+
+    /// Compares the last GET against the patch before it, recording a finding on mismatch.
+    fn check_read_after_write(&self, endpoint: &Endpoint, steps: &[Step]) -> bool {
+        let get = &steps[steps.len() - 1];
+        let sent = steps[steps.len() - 2].payload.as_ref().unwrap();
+
+        let message = match serde_json::from_str::<Value>(&get.body) {
+            Err(_) => format!("GET {} did not return JSON", get.url),
+            Ok(stored) => {
+                let mismatches = endpoint.mismatches(sent, &stored);
+                if mismatches.is_empty() {
+                    return false;
+                }
+                format!("fields not stored as sent: {}", mismatches.join(", "))
+            }
+        };
+
+        self.findings.lock().unwrap().record(Finding {
+            kind: FindingKind::ReadAfterWriteMismatch,
+            endpoint: endpoint.name.clone(),
+            message,
+            steps: steps.to_vec(),
+        });
+        true
+    }
+
+    /// Records a finding if the last step failed, returning whether the sequence should stop.
+    fn check_step(&self, endpoint: &Endpoint, steps: &[Step]) -> bool {
+        let last = steps.last().unwrap();
+        let (kind, message) = match (last.status, &last.error) {
+            (_, Some(RestClientError::Timeout(_))) => {
+                (FindingKind::Timeout, format!("{} {} timed out", last.method, last.url))
+            }
+            (None, error) => {
+                self.findings.lock().unwrap().record_error(&endpoint.name, error.as_ref().unwrap());
+                return true;
+            }
+            (Some(status), _) if status >= 500 => {
+                (FindingKind::ServerError, format!("{} {} returned {}", last.method, last.url, status))
+            }
+            (Some(_), _) => return false,
+        };
+
+        self.findings.lock().unwrap().record(Finding {
+            kind,
+            endpoint: endpoint.name.clone(),
+            message,
+            steps: steps.to_vec(),
+        });
+        true
+    }
+
+    /// Patches with random values for the fuzzable session and endpoint parameters.
+    fn patch(&self, endpoint: &Endpoint, url: &str, payload: Value) -> Step {
+        self.count_request(endpoint);
+        let options = self.session.parameters.randomized_options()
+            .merged_with(&endpoint.parameters.randomized_options());
+        let result = self.client.request_with(Method::Patch, url, Some(&payload), &options);
+        Step::new("PATCH", url, Some(payload), result).with_options(self.recorded(endpoint, &options))
+    }
+
+    fn get(&self, endpoint: &Endpoint, url: &str) -> Step {
+        self.count_request(endpoint);
+        let options = endpoint.parameters.default_options();
+        let result = self.client.request_with(Method::Get, url, None, &options);
+        Step::new("GET", url, None, result).with_options(self.recorded(endpoint, &options))
+    }
+
+    /// The options as written to the findings, without what their `${...}`s expanded to.
+    fn recorded(&self, endpoint: &Endpoint, options: &RequestOptions) -> RequestOptions {
+        endpoint.parameters.recorded(&self.session.parameters.recorded(options))
+    }
+
+    fn count_request(&self, endpoint: &Endpoint) {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.endpoint_requests[&endpoint.name].fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn is_success(status: Option<u16>) -> bool {
+    status.map_or(false, |s| s >= 200 && s < 300)
+}
+
+#[cfg(test)]
+mod fuzzer {
+    use rest_client::config_builder::ClientConfigurationBuilder;
+    use rest_client::response::RestResponse;
+    use rest_client::transport::InMemoryTransport;
+
+    use super::*;
+
+    fn castle_session() -> Session {
+        let mut session = Session::new(&json!({
+            "root_url": "http://castles",
+            "endpoints": [
+                { "name": "castle", "url": "/castles", "requires": [],
+                  "schema": [{ "name": "name", "kind": "string", "default_value": "Jumbo" }] }
+            ]
+        }));
+        session.created.insert("castle".to_owned(), json!({ "id": 1, "name": "Jumbo" }));
+        session
+    }
+
+    #[test]
+    fn writes_which_are_not_stored_are_findings() {
+        let transport = InMemoryTransport::new()
+            .route(Method::Patch, "/castles/1", |_| RestResponse::from_json(200, &json!({})))
+            .route(Method::Get, "/castles/1", |_| RestResponse::from_json(200, &json!({ "id": 1, "name": "Jumbo" })));
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(transport)
+            .build()
+            .unwrap();
+        let session = castle_session();
+
+        let fuzzer = Fuzzer::new(&session, &client).verify_writes(true);
+        fuzzer.run(Some(1));
+
+        let findings = fuzzer.finish().1.to_json();
+        assert_eq!(findings[0]["kind"], "read_after_write_mismatch");
+        assert_eq!(findings[0]["message"], "fields not stored as sent: name");
+        assert_eq!(findings[0]["steps"][0]["method"], "PATCH");
+        assert_eq!(findings[0]["steps"][1]["method"], "GET");
+    }
+
+    #[test]
+    fn server_errors_stop_a_sequence() {
+        let transport = InMemoryTransport::new()
+            .route(Method::Patch, "/castles/1", |_| RestResponse::from_json(500, &json!({})));
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(transport)
+            .build()
+            .unwrap();
+        let session = castle_session();
+
+        let fuzzer = Fuzzer::new(&session, &client).sequence_length(3);
+        fuzzer.run(Some(2));
+
+        let (_, findings) = fuzzer.finish();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings.to_json()[0]["steps"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn workers_share_sequences_by_endpoint_weight() {
+        let mut session = Session::new(&json!({
+            "root_url": "http://castles",
+            "endpoints": [
+                { "name": "castle", "url": "/castles", "requires": [], "weight": 3,
+                  "schema": [{ "name": "name", "kind": "string", "default_value": "Jumbo" }] },
+                { "name": "moat", "url": "/moats", "requires": [],
+                  "schema": [{ "name": "depth", "kind": "number", "default_value": 3 }] },
+                { "name": "tower", "url": "/towers", "requires": [], "weight": 0,
+                  "schema": [{ "name": "height", "kind": "number", "default_value": 3 }] }
+            ]
+        }));
+        for name in &["castle", "moat", "tower"] {
+            session.created.insert(name.to_string(), json!({ "id": 1 }));
+        }
+        let transport = Arc::new(InMemoryTransport::new()
+            .route(Method::Patch, "/*/1", |_| RestResponse::from_json(200, &json!({}))));
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        let fuzzer = Fuzzer::new(&session, &client).workers(4);
+        let (progress, stopped_by) = fuzzer.run(Some(5));
+
+        let urls: Vec<String> = transport.requests().into_iter().map(|r| r.url).collect();
+        assert_eq!(urls.iter().filter(|u| u.ends_with("/castles/1")).count(), 15);
+        assert_eq!(urls.iter().filter(|u| u.ends_with("/moats/1")).count(), 5);
+        assert_eq!(urls.len(), 20);
+        assert_eq!(progress.endpoint_requests["castle"], 15);
+        assert_eq!(stopped_by, "finished 5 iterations");
+        assert_eq!(fuzzer.finish().0.to_json()["castle"]["payloads_sent"], 15);
+    }
+
+    #[test]
+    fn nothing_is_sent_once_stopped() {
+        let transport = Arc::new(InMemoryTransport::new());
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let session = castle_session();
+
+        let fuzzer = Fuzzer::new(&session, &client).workers(2).stop_flag(Arc::new(AtomicBool::new(true)));
+        let (progress, stopped_by) = fuzzer.run(Some(10));
+
+        assert!(transport.requests().is_empty());
+        assert_eq!(progress.requests, 0);
+        assert_eq!(stopped_by, "interrupted");
+    }
+
+    #[test]
+    fn unbounded_runs_stop_when_the_budget_is_spent() {
+        let transport = InMemoryTransport::new()
+            .route(Method::Patch, "/castles/1", |_| RestResponse::from_json(500, &json!({})));
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(transport)
+            .build()
+            .unwrap();
+        let session = castle_session();
+
+        let budget = Budget { requests: Some(7), ..Budget::default() };
+        let fuzzer = Fuzzer::new(&session, &client).workers(2).budget(budget);
+        let (progress, stopped_by) = fuzzer.run(None);
+
+        assert!(progress.requests >= 7 && progress.requests <= 8, "sent {}", progress.requests);
+        assert_eq!(progress.distinct_failures, 1);
+        assert_eq!(stopped_by, format!("sent {} requests", progress.requests));
+    }
+
+    #[test]
+    fn fuzzable_parameters_are_randomized_and_recorded() {
+        std::env::set_var("VACCINE_TEST_FUZZER_KEY", "s3cret");
+        let mut session = Session::new(&json!({
+            "root_url": "http://castles",
+            "headers": { "X-Tenant-Id": "7", "X-Api-Key": "${VACCINE_TEST_FUZZER_KEY}" },
+            "endpoints": [
+                { "name": "castle", "url": "/castles", "requires": [],
+                  "query": { "dry_run": { "kind": "boolean", "default_value": false } },
+                  "schema": [{ "name": "name", "kind": "string", "default_value": "Jumbo" }] }
+            ]
+        }));
+        session.created.insert("castle".to_owned(), json!({ "id": 1 }));
+        let transport = InMemoryTransport::new()
+            .route(Method::Patch, "/castles/1", |_| RestResponse::from_json(500, &json!({})));
+        let client = ClientConfigurationBuilder::new()
+            .root_url("http://castles")
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        let fuzzer = Fuzzer::new(&session, &client);
+        fuzzer.run(Some(1));
+
+        let steps = &fuzzer.finish().1.to_json()[0]["steps"];
+        assert_eq!(steps[0]["options"]["headers"], json!({ "x-tenant-id": "7", "x-api-key": "${VACCINE_TEST_FUZZER_KEY}" }));
+        assert!(steps[0]["options"]["query"]["dry_run"] == "true" || steps[0]["options"]["query"]["dry_run"] == "false");
+    }
+}
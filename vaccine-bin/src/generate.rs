@@ -0,0 +1,177 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+
+use schema_parser::configuration::Session;
+use schema_parser::expectations::MutationClass;
+use schema_parser::random_values::{generate_weighted_boolean, seed};
+
+use crate::fraction;
+
+/// Which payloads to print for each endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerateOptions {
+    pub count: usize,
+    pub defaults: bool,
+    pub randomized: bool,
+    /// Fraction of randomized payloads with a field of the wrong type, as in a fuzzing run.
+    pub type_confusion_rate: f64,
+    /// Only these endpoints, or all of them when empty.
+    pub endpoints: Vec<String>,
+}
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("generate")
+        .about("Prints the payloads a run would send as JSON lines, without sending any requests")
+        .arg(Arg::with_name("schema")
+            .help("path to the JSON, YAML or TOML schema file")
+            .required(true))
+        .arg(Arg::with_name("profile")
+            .long("profile")
+            .takes_value(true)
+            .help("entry of the schema's `profiles` to generate for"))
+        .arg(Arg::with_name("count")
+            .short("n")
+            .long("count")
+            .takes_value(true)
+            .default_value("1")
+            .help("payloads of each kind printed per endpoint"))
+        .arg(Arg::with_name("kind")
+            .long("kind")
+            .takes_value(true)
+            .possible_values(&["default", "randomized", "all"])
+            .default_value("all")
+            .help("print default payloads, randomized ones or both"))
+        .arg(Arg::with_name("type-confusion-rate")
+            .long("type-confusion-rate")
+            .takes_value(true)
+            .default_value("0")
+            .validator(fraction)
+            .help("fraction of randomized payloads with a field of the wrong type, between 0 and 1"))
+        .arg(Arg::with_name("endpoint")
+            .long("endpoint")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("only generate for this endpoint, may be given more than once"))
+        .arg(Arg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .help("seed for the random values, so that the same payloads are printed again"))
+}
+
+pub fn run(matches: &ArgMatches) -> i32 {
+    let session = Session::from_file_with_profile(matches.value_of("schema").unwrap(), matches.value_of("profile"));
+    let kind = matches.value_of("kind").unwrap();
+    let options = GenerateOptions {
+        count: value_t!(matches, "count", usize).unwrap_or_else(|e| e.exit()),
+        defaults: kind != "randomized",
+        randomized: kind != "default",
+        type_confusion_rate: value_t!(matches, "type-confusion-rate", f64).unwrap_or_else(|e| e.exit()),
+        endpoints: matches.values_of("endpoint").map_or(vec![], |e| e.map(str::to_owned).collect()),
+    };
+    if let Some(unknown) = options.endpoints.iter().find(|e| !session.endpoints.contains_key(e.as_str())) {
+        eprintln!("unknown endpoint {}", unknown);
+        return 1;
+    }
+    if matches.is_present("seed") {
+        seed(value_t!(matches, "seed", u64).unwrap_or_else(|e| e.exit()));
+    }
+
+    for line in payloads(&session, &options) {
+        println!("{}", line);
+    }
+    0
+}
+
+/// One JSON object per payload, naming its endpoint and whether it is a `default`, `valid` or
+/// `type_confused` payload. Endpoints are taken in name order.
+pub fn payloads(session: &Session, options: &GenerateOptions) -> Vec<Value> {
+    let mut names: Vec<&String> = session.endpoints.keys()
+        .filter(|name| options.endpoints.is_empty() || options.endpoints.contains(name))
+        .collect();
+    names.sort();
+
+    let mut lines = Vec::new();
+    for name in names {
+        let endpoint = &session.endpoints[name];
+        if options.defaults {
+            for _ in 0..options.count {
+                lines.push(json!({ "endpoint": name, "class": "default", "payload": endpoint.default_payload() }));
+            }
+        }
+        if options.randomized {
+            for _ in 0..options.count {
+                let class = if generate_weighted_boolean(options.type_confusion_rate) {
+                    MutationClass::TypeConfused
+                } else {
+                    MutationClass::Valid
+                };
+                lines.push(json!({ "endpoint": name, "class": class.as_str(), "payload": endpoint.payload_for(class) }));
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod generate {
+    use super::*;
+
+    fn session() -> Session {
+        Session::new(&json!({
+            "root_url": "http://localhost:8000",
+            "endpoints": [
+                { "name": "moat", "url": "/moats", "requires": ["castle"], "schema": [
+                    { "name": "depth", "kind": "number", "default_value": 3 }
+                ] },
+                { "name": "castle", "url": "/castles", "requires": [], "schema": [
+                    { "name": "name", "kind": "string", "default_value": "Camelot" },
+                    { "name": "towers", "kind": "number", "default_value": 4 },
+                    { "name": "haunted", "kind": "boolean", "default_value": false }
+                ] },
+                { "name": "kingdom", "url": "/kingdoms", "requires": [], "schema": [] }
+            ]
+        }))
+    }
+
+    fn options() -> GenerateOptions {
+        GenerateOptions { count: 2, defaults: true, randomized: true, type_confusion_rate: 0.0, endpoints: vec![] }
+    }
+
+    #[test]
+    fn payloads_are_printed_per_endpoint() {
+        let lines = payloads(&session(), &options());
+        let summary: Vec<(&str, &str)> = lines.iter()
+            .map(|l| (l["endpoint"].as_str().unwrap(), l["class"].as_str().unwrap()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("castle", "default"), ("castle", "default"), ("castle", "valid"), ("castle", "valid"),
+            ("kingdom", "default"), ("kingdom", "default"), ("kingdom", "valid"), ("kingdom", "valid"),
+            ("moat", "default"), ("moat", "default"), ("moat", "valid"), ("moat", "valid"),
+        ]);
+        assert_eq!(lines[0]["payload"], json!({ "name": "Camelot", "towers": 4, "haunted": false }));
+        assert_eq!(lines[6]["payload"], json!({}));
+
+        let options = GenerateOptions {
+            defaults: false,
+            type_confusion_rate: 1.0,
+            endpoints: vec!["moat".to_owned()],
+            ..options()
+        };
+        let lines = payloads(&session(), &options);
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l["class"] == "type_confused" && !l["payload"]["depth"].is_i64()));
+    }
+
+    #[test]
+    fn seeded_payloads_repeat() {
+        let session = session();
+        let options = GenerateOptions { count: 20, type_confusion_rate: 0.5, ..options() };
+        seed(42);
+        let first = payloads(&session, &options);
+        seed(42);
+        assert_eq!(payloads(&session, &options), first);
+        seed(43);
+        assert_ne!(payloads(&session, &options), first);
+    }
+}
@@ -0,0 +1,358 @@
+#[macro_use]
+extern crate clap;
+#[macro_use]
+extern crate serde_json;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use clap::{App, AppSettings, Arg, ArgMatches};
+
+use rest_client::config_builder::ClientConfigurationBuilder;
+use rest_client::RestClient;
+use rest_client::response::RestClientError;
+use rest_client::transport::HttpVersion;
+use schema_parser::configuration::Session;
+use schema_parser::dependency_resolver::{build_object_graph, teardown_object_graph};
+
+use crate::campaign::Budget;
+use crate::credentials::{
+    CommandProvider, CredentialError, CredentialProvider, Credentials, EnvProvider, FileProvider, PromptProvider,
+    resolve_credentials,
+};
+use crate::fuzzer::Fuzzer;
+
+mod campaign;
+mod credentials;
+mod coverage;
+mod findings;
+mod fuzzer;
+mod generate;
+mod validate;
+
+fn seconds(matches: &ArgMatches, name: &str) -> Option<Duration> {
+    matches.value_of(name).map(|_| {
+        let seconds = value_t!(matches, name, f64).unwrap_or_else(|e| e.exit());
+        Duration::from_millis((seconds * 1000.0) as u64)
+    })
+}
+
+fn optional_value(matches: &ArgMatches, name: &str) -> Option<usize> {
+    matches.value_of(name).map(|_| value_t!(matches, name, usize).unwrap_or_else(|e| e.exit()))
+}
+
+fn positive_number(v: String) -> Result<(), String> {
+    match v.parse::<f64>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(()),
+        _ => Err(format!("expected a number greater than 0, got {}", v)),
+    }
+}
+
+fn fraction(v: String) -> Result<(), String> {
+    match v.parse::<f64>() {
+        Ok(n) if (0.0..=1.0).contains(&n) => Ok(()),
+        _ => Err(format!("expected a number between 0 and 1, got {}", v)),
+    }
+}
+
+fn valid_duration(v: String) -> Result<(), String> {
+    humantime::parse_duration(&v).map(|_| ()).map_err(|e| format!("{} is not a duration like 30m or 1h 30m: {}", v, e))
+}
+
+fn build_client(session: &Session, matches: &ArgMatches) -> Result<RestClient, RestClientError> {
+    let mut builder = ClientConfigurationBuilder::new()
+        .root_url(&session.root_url)
+        .options(session.parameters.default_options())
+        .keep_alive(!matches.is_present("no-keep-alive"));
+    if let Some(auth_type) = session.auth_type.clone() {
+        builder = builder.auth_type(auth_type);
+    }
+    if let Some(login) = session.login.clone() {
+        builder = builder.login(login);
+    }
+    if let Some(timeout) = seconds(matches, "connect-timeout") {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = seconds(matches, "read-timeout") {
+        builder = builder.read_timeout(timeout);
+    }
+    if let Some(timeout) = seconds(matches, "total-timeout") {
+        builder = builder.total_timeout(timeout);
+    }
+    if let Some(pool_size) = optional_value(matches, "pool-size") {
+        builder = builder.pool_size(pool_size);
+    }
+    if matches.is_present("http2") {
+        builder = builder.http_version(HttpVersion::Http2);
+    }
+    if matches.is_present("rate") {
+        let rate = value_t!(matches, "rate", f64).unwrap_or_else(|e| e.exit());
+        let burst = optional_value(matches, "burst").unwrap_or(1);
+        builder = builder.rate_limit(rate, burst);
+    }
+    if let Some(path) = matches.value_of("ca-bundle") {
+        builder = builder.ca_bundle(path);
+    }
+    if let (Some(cert), Some(key)) = (matches.value_of("client-cert"), matches.value_of("client-key")) {
+        builder = builder.client_pem(cert, key);
+    }
+    if let Some(path) = matches.value_of("client-pkcs12") {
+        let password = env::var("VACCINE_PKCS12_PASSWORD").unwrap_or_default();
+        builder = builder.client_pkcs12(path, &password);
+    }
+    builder = builder.insecure_skip_verify(matches.is_present("insecure"));
+
+    let credentials = credential_providers(matches)
+        .and_then(|providers| resolve_credentials(session, &providers))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+    match credentials {
+        None => builder.build(),
+        Some(Credentials::Token(token)) => builder.token(&token).build(),
+        Some(Credentials::UsernamePassword { username, password }) => {
+            builder.basic_auth(&username, &password).build()
+        }
+    }
+}
+
+/// Where credentials are looked for, in order: the helper command, the credentials file,
+/// the environment and finally standard input.
+fn credential_providers(matches: &ArgMatches) -> Result<Vec<Box<dyn CredentialProvider>>, CredentialError> {
+    let mut providers: Vec<Box<dyn CredentialProvider>> = Vec::new();
+    if let Some(command) = matches.value_of("credentials-command") {
+        providers.push(Box::new(CommandProvider::new(command)));
+    }
+    if let Some(path) = matches.value_of("credentials-file") {
+        let profile = matches.value_of("credentials-profile")
+            .or_else(|| matches.value_of("profile"))
+            .unwrap_or("default");
+        providers.push(Box::new(FileProvider::new(Path::new(path), profile)?));
+    }
+    providers.push(Box::new(EnvProvider::default()));
+    if matches.is_present("prompt-credentials") {
+        providers.push(Box::new(PromptProvider));
+    }
+    Ok(providers)
+}
+
+fn main() {
+    let matches = App::new("vaccine")
+        .version(crate_version!())
+        .about("Sends randomized patch requests to REST APIs described by a schema")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(validate::subcommand())
+        .subcommand(generate::subcommand())
+        .arg(Arg::with_name("schema")
+            .help("path to the JSON, YAML or TOML schema file")
+            .required(true))
+        .arg(Arg::with_name("profile")
+            .long("profile")
+            .takes_value(true)
+            .help("entry of the schema's `profiles` to run against"))
+        .arg(Arg::with_name("iterations")
+            .short("n")
+            .long("iterations")
+            .takes_value(true)
+            .help("number of randomized patch sequences to send to each endpoint, 100 unless a budget is given"))
+        .arg(Arg::with_name("duration")
+            .long("duration")
+            .takes_value(true)
+            .validator(valid_duration)
+            .help("stop after fuzzing for this long, e.g. 30m"))
+        .arg(Arg::with_name("max-failures")
+            .long("max-failures")
+            .takes_value(true)
+            .help("stop after finding this many distinct failures"))
+        .arg(Arg::with_name("max-requests")
+            .long("max-requests")
+            .takes_value(true)
+            .help("stop after sending this many requests"))
+        .arg(Arg::with_name("progress-interval")
+            .long("progress-interval")
+            .takes_value(true)
+            .default_value("10")
+            .help("seconds between progress reports, 0 to turn them off"))
+        .arg(Arg::with_name("sequence-length")
+            .long("sequence-length")
+            .takes_value(true)
+            .default_value("1")
+            .help("number of successive patches applied to the same object, fetching it in between"))
+        .arg(Arg::with_name("type-confusion-rate")
+            .long("type-confusion-rate")
+            .takes_value(true)
+            .default_value("0")
+            .validator(fraction)
+            .help("fraction of patches sent with a field of the wrong type, between 0 and 1"))
+        .arg(Arg::with_name("verify-writes")
+            .long("verify-writes")
+            .help("fetch the object after every successful patch and check that it holds what was sent"))
+        .arg(Arg::with_name("keep")
+            .long("keep")
+            .help("keep the created objects instead of deleting them after the run"))
+        .arg(Arg::with_name("findings")
+            .long("findings")
+            .takes_value(true)
+            .help("write every finding, with the requests leading up to it, as JSON to this file"))
+        .arg(Arg::with_name("connect-timeout")
+            .long("connect-timeout")
+            .takes_value(true)
+            .help("seconds to wait for a connection to be established"))
+        .arg(Arg::with_name("read-timeout")
+            .long("read-timeout")
+            .takes_value(true)
+            .help("seconds to wait for the response and for each read of its body (30 by default)"))
+        .arg(Arg::with_name("total-timeout")
+            .long("total-timeout")
+            .takes_value(true)
+            .help("seconds a whole request may take before it is reported as a timeout"))
+        .arg(Arg::with_name("pool-size")
+            .long("pool-size")
+            .takes_value(true)
+            .help("idle connections kept open per host"))
+        .arg(Arg::with_name("no-keep-alive")
+            .long("no-keep-alive")
+            .help("open a new connection for every request"))
+        .arg(Arg::with_name("http2")
+            .long("http2")
+            .help("talk HTTP/2 to the server without negotiating it first"))
+        .arg(Arg::with_name("ca-bundle")
+            .long("ca-bundle")
+            .takes_value(true)
+            .help("PEM file of certificates to trust on top of the system roots"))
+        .arg(Arg::with_name("client-cert")
+            .long("client-cert")
+            .takes_value(true)
+            .requires("client-key")
+            .help("PEM client certificate, optionally followed by its chain"))
+        .arg(Arg::with_name("client-key")
+            .long("client-key")
+            .takes_value(true)
+            .requires("client-cert")
+            .help("PEM private key of the client certificate"))
+        .arg(Arg::with_name("client-pkcs12")
+            .long("client-pkcs12")
+            .takes_value(true)
+            .conflicts_with("client-cert")
+            .help("PKCS#12 client certificate and key, unlocked with VACCINE_PKCS12_PASSWORD"))
+        .arg(Arg::with_name("credentials-file")
+            .long("credentials-file")
+            .takes_value(true)
+            .help("INI file of credentials, read before the VACCINE_* environment variables"))
+        .arg(Arg::with_name("credentials-profile")
+            .long("credentials-profile")
+            .takes_value(true)
+            .help("section of the credentials file to use, --profile or `default` if not given"))
+        .arg(Arg::with_name("credentials-command")
+            .long("credentials-command")
+            .takes_value(true)
+            .help("command printing the credential named by its last argument, asked before anything else"))
+        .arg(Arg::with_name("prompt-credentials")
+            .long("prompt-credentials")
+            .help("read credentials not found elsewhere from standard input"))
+        .arg(Arg::with_name("insecure")
+            .long("insecure")
+            .help("accept any server certificate; only for test servers you control"))
+        .arg(Arg::with_name("workers")
+            .short("w")
+            .long("workers")
+            .takes_value(true)
+            .default_value("1")
+            .help("number of threads sending sequences at the same time"))
+        .arg(Arg::with_name("rate")
+            .long("rate")
+            .takes_value(true)
+            .validator(positive_number)
+            .help("most requests sent per second, across all workers"))
+        .arg(Arg::with_name("burst")
+            .long("burst")
+            .takes_value(true)
+            .requires("rate")
+            .help("requests which may be sent at once after idling, without waiting on the rate (1 by default)"))
+        .arg(Arg::with_name("coverage-json")
+            .long("coverage-json")
+            .takes_value(true)
+            .help("write the field coverage report as JSON to this file"))
+        .get_matches();
+
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        process::exit(validate::run(matches));
+    }
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        process::exit(generate::run(matches));
+    }
+
+    let budget = Budget {
+        duration: matches.value_of("duration").map(|d| humantime::parse_duration(d).unwrap()),
+        distinct_failures: optional_value(&matches, "max-failures"),
+        requests: optional_value(&matches, "max-requests"),
+    };
+    let iterations = match optional_value(&matches, "iterations") {
+        None if budget.is_unbounded() => Some(100),
+        iterations => iterations,
+    };
+    let progress_interval = seconds(&matches, "progress-interval").filter(|i| *i > Duration::from_secs(0));
+    let sequence_length = value_t!(matches, "sequence-length", usize).unwrap_or_else(|e| e.exit());
+    let type_confusion_rate = value_t!(matches, "type-confusion-rate", f64).unwrap_or_else(|e| e.exit());
+    let workers = value_t!(matches, "workers", usize).unwrap_or_else(|e| e.exit());
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    ctrlc::set_handler(move || {
+        if handler_stop.swap(true, Ordering::SeqCst) {
+            eprintln!("interrupted again, exiting without writing reports or deleting objects");
+            process::exit(130);
+        }
+        eprintln!("interrupted, finishing the sequences in flight...");
+    }).expect("failed to set the Ctrl-C handler");
+
+    let mut session = Session::from_file_with_profile(matches.value_of("schema").unwrap(), matches.value_of("profile"));
+    let client = build_client(&session, &matches).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let creations = build_object_graph(&mut session, &client).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let fuzzer = Fuzzer::new(&session, &client)
+        .sequence_length(sequence_length)
+        .verify_writes(matches.is_present("verify-writes"))
+        .type_confusion_rate(type_confusion_rate)
+        .workers(workers)
+        .stop_flag(stop)
+        .budget(budget)
+        .progress_interval(progress_interval);
+    fuzzer.check_creations(&creations);
+    let (progress, stopped_by) = fuzzer.run(iterations);
+    let (coverage, findings) = fuzzer.finish();
+
+    print!("{}", coverage.render_table());
+    print!("{}", progress.render_summary(&stopped_by));
+    print!("{}", findings.render_summary());
+
+    if let Some(path) = matches.value_of("findings") {
+        findings.write_to(path);
+    }
+    if let Some(path) = matches.value_of("coverage-json") {
+        let mut file = File::create(path).expect(&format!("failed to create {}", path));
+        let report = serde_json::to_string_pretty(&coverage.to_json()).unwrap();
+        file.write_all(report.as_bytes()).expect(&format!("failed to write {}", path));
+    }
+
+    if !matches.is_present("keep") {
+        let failures = teardown_object_graph(&mut session, &client);
+        for failure in &failures {
+            eprintln!("failed to delete {} at {}: {}", failure.endpoint, failure.url, failure.reason);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use schema_parser::configuration::Session;
+use schema_parser::validation::validate_file;
+
+pub fn subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("validate")
+        .about("Checks a schema for problems without sending any requests")
+        .arg(Arg::with_name("schema")
+            .help("path to the JSON, YAML or TOML schema file")
+            .required(true))
+        .arg(Arg::with_name("profile")
+            .long("profile")
+            .takes_value(true)
+            .help("entry of the schema's `profiles` to check"))
+}
+
+/// Prints every problem with the schema, returning the exit code.
+pub fn run(matches: &ArgMatches) -> i32 {
+    let path = matches.value_of("schema").unwrap();
+    let profile = matches.value_of("profile");
+
+    let problems = validate_file(path, profile);
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    if !problems.is_empty() {
+        eprintln!("{} problem(s) found", problems.len());
+        return 1;
+    }
+
+    let session = Session::from_file_with_profile(path, profile);
+    println!("{}: ok, {} endpoints", path, session.endpoints.len());
+    0
+}